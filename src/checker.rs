@@ -0,0 +1,27 @@
+#![allow(unused)]
+
+use crate::core::check::{Checker, LineChecker, Tokens, Verdict};
+
+/// Checks the output of wr, given the input and the output of cr. Used with `--checker rust`.
+///
+/// Edit this for problems with several correct answers. For example, when any pair of indices
+/// whose values sum up to `k` is accepted:
+/// ```ignore
+/// let check = || -> Result<(), Verdict> {
+///     let mut input = Tokens::jury(input);
+///     let (n, k): (usize, i64) = (input.read()?, input.read()?);
+///     let a: Vec<i64> = input.read_vec(n)?;
+///
+///     let mut output = Tokens::output(wr_out);
+///     let (i, j): (usize, usize) = (output.read()?, output.read()?);
+///     output.expect_end()?;
+///     if i == j || i > n || j > n || a[i - 1] + a[j - 1] != k {
+///         return Err(Verdict::WrongAnswer(format!("{i} {j} isn't a valid pair")));
+///     }
+///     Ok(())
+/// };
+/// check().into()
+/// ```
+pub fn check(input: &str, cr_out: &str, wr_out: &str) -> Verdict {
+    LineChecker.check(input, cr_out.as_bytes(), wr_out.as_bytes())
+}
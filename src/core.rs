@@ -1,3 +1,9 @@
+pub mod check;
 pub mod compile;
+pub mod config;
+pub mod diff;
+pub mod doctor;
+pub mod generator;
 pub mod run_code;
+pub mod stream;
 pub mod string;
@@ -0,0 +1,729 @@
+use std::{
+    any::type_name,
+    collections::HashMap,
+    fmt, fs,
+    io::{self, Result},
+    path::PathBuf,
+    process::{Command, Stdio},
+    str::{FromStr, SplitWhitespace},
+    time::Duration,
+};
+
+use crate::core::{
+    compile::RunLang,
+    run_code::{invocation, own_process_group, random_name, wait_or_kill},
+    string::{escape_bytes, process_bytes},
+};
+
+/// The time a special judge may take to check a single output.
+pub const SPECIAL_JUDGE_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// The judgement of the output of wr against the one of cr.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Accepted,
+    /// The output is wrong, with the reason given by the checker.
+    WrongAnswer(String),
+    /// The output is right except for its format, e.g. whitespace.
+    PresentationError(String),
+    /// The checker itself failed, e.g. because the correct answer is invalid.
+    Fail(String),
+}
+
+impl Verdict {
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Verdict::Accepted)
+    }
+
+    /// Returns the reason given by the checker, which is empty for an accepted output.
+    pub fn message(&self) -> &str {
+        match self {
+            Verdict::Accepted => "",
+            Verdict::WrongAnswer(message)
+            | Verdict::PresentationError(message)
+            | Verdict::Fail(message) => message,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Verdict::Accepted => "Accepted",
+            Verdict::WrongAnswer(_) => "Wrong Answer",
+            Verdict::PresentationError(_) => "Presentation Error",
+            Verdict::Fail(_) => "Checker Failure",
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message() {
+            "" => write!(f, "{}", self.name()),
+            message => write!(f, "{}: {message}", self.name()),
+        }
+    }
+}
+
+impl From<std::result::Result<(), Verdict>> for Verdict {
+    fn from(result: std::result::Result<(), Verdict>) -> Self {
+        result.err().unwrap_or(Verdict::Accepted)
+    }
+}
+
+/// Reads whitespace-separated tokens from an input or an output, for writing checkers.
+///
+/// A malformed output of wr is a wrong answer, while a malformed input or output of cr is a failure
+/// of the checker, so the errors are returned as the verdict to give.
+pub struct Tokens<'a> {
+    tokens: SplitWhitespace<'a>,
+    /// The number of tokens read so far.
+    count: usize,
+    from_jury: bool,
+}
+
+impl<'a> Tokens<'a> {
+    /// Reads the output of wr.
+    pub fn output(s: &'a str) -> Self {
+        Self {
+            tokens: s.split_whitespace(),
+            count: 0,
+            from_jury: false,
+        }
+    }
+
+    /// Reads the input or the output of cr.
+    pub fn jury(s: &'a str) -> Self {
+        Self {
+            from_jury: true,
+            ..Self::output(s)
+        }
+    }
+
+    fn error(&self, message: String) -> Verdict {
+        match self.from_jury {
+            true => Verdict::Fail(message),
+            false => Verdict::WrongAnswer(message),
+        }
+    }
+
+    /// Returns the next token as it is.
+    pub fn token(&mut self) -> std::result::Result<&'a str, Verdict> {
+        let token = self.tokens.next().ok_or_else(|| {
+            self.error(format!(
+                "Expected token {}, found the end of the output",
+                self.count + 1
+            ))
+        })?;
+        self.count += 1;
+        Ok(token)
+    }
+
+    /// Parses the next token, e.g. as an integer.
+    pub fn read<T: FromStr>(&mut self) -> std::result::Result<T, Verdict> {
+        let token = self.token()?;
+        token.parse().map_err(|_| {
+            self.error(format!(
+                "Expected {} at token {}, found {token}",
+                type_name::<T>(),
+                self.count
+            ))
+        })
+    }
+
+    /// Parses the next `n` tokens.
+    pub fn read_vec<T: FromStr>(&mut self, n: usize) -> std::result::Result<Vec<T>, Verdict> {
+        (0..n).map(|_| self.read()).collect()
+    }
+
+    /// Checks that there are no more tokens.
+    pub fn expect_end(&mut self) -> std::result::Result<(), Verdict> {
+        match self.tokens.next() {
+            None => Ok(()),
+            Some(token) => Err(self.error(format!(
+                "Expected the end of the output, found {token} at token {}",
+                self.count + 1
+            ))),
+        }
+    }
+}
+
+/// Decides whether an output is correct, given the input and the output of cr. The outputs are
+/// given as they were printed, which may not be valid UTF-8.
+///
+/// Any `Fn(&str, &str, &str) -> Verdict` can be used as a checker, which is given the outputs
+/// decoded as UTF-8.
+pub trait Checker: Send + Sync {
+    fn check(&self, input: &str, expected: &[u8], actual: &[u8]) -> Verdict;
+}
+
+impl<F> Checker for F
+where
+    F: Fn(&str, &str, &str) -> Verdict + Send + Sync,
+{
+    fn check(&self, input: &str, expected: &[u8], actual: &[u8]) -> Verdict {
+        match (std::str::from_utf8(expected), std::str::from_utf8(actual)) {
+            (Err(e), _) => Verdict::Fail(format!(
+                "The correct answer isn't valid UTF-8 from byte {}",
+                e.valid_up_to()
+            )),
+            (_, Err(e)) => Verdict::WrongAnswer(format!(
+                "The output isn't valid UTF-8 from byte {}",
+                e.valid_up_to()
+            )),
+            (Ok(expected), Ok(actual)) => self(input, expected, actual),
+        }
+    }
+}
+
+/// Compares the outputs line by line after removing trailing whitespace of each line and
+/// trailing blank lines, as `process_str` does. This is how most online judges compare.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LineChecker;
+
+impl Checker for LineChecker {
+    fn check(&self, _input: &str, expected: &[u8], actual: &[u8]) -> Verdict {
+        let expected = process_bytes(expected);
+        let actual = process_bytes(actual);
+        if expected == actual {
+            return Verdict::Accepted;
+        }
+
+        let mut expected_lines = lines(&expected);
+        let mut actual_lines = lines(&actual);
+        for line in 1.. {
+            match (expected_lines.next(), actual_lines.next()) {
+                (Some(x), Some(y)) if x == y => continue,
+                (Some(_), Some(_)) => {
+                    return Verdict::WrongAnswer(format!("Line {line} differs"));
+                }
+                (Some(_), None) => {
+                    return Verdict::WrongAnswer(format!("Output ends early at line {line}"));
+                }
+                (None, _) => {
+                    return Verdict::WrongAnswer(format!("Extra output from line {line}"));
+                }
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// The error allowed by `FloatChecker` by default, both absolute and relative.
+pub const FLOAT_ERROR_DEFAULT: f64 = 1e-6;
+
+/// Compares the outputs token by token, where tokens are separated by whitespace. Tokens which
+/// are both numbers are equal if they're within the absolute or the relative error, and the others
+/// must be exactly equal.
+#[derive(Clone, Copy, Debug)]
+pub struct FloatChecker {
+    pub abs_error: f64,
+    pub rel_error: f64,
+}
+
+impl Default for FloatChecker {
+    fn default() -> Self {
+        Self {
+            abs_error: FLOAT_ERROR_DEFAULT,
+            rel_error: FLOAT_ERROR_DEFAULT,
+        }
+    }
+}
+
+impl Checker for FloatChecker {
+    fn check(&self, _input: &str, expected: &[u8], actual: &[u8]) -> Verdict {
+        check_tokens(expected, actual, |x, y| {
+            match (parse_number(x), parse_number(y)) {
+                (Some(a), Some(b)) => {
+                    let abs = (a - b).abs();
+                    let rel = abs / a.abs();
+                    (abs > self.abs_error && rel > self.rel_error)
+                        .then(|| format!("absolute error {abs:e}, relative error {rel:e}"))
+                }
+                _ => (x != y).then(String::new),
+            }
+        })
+    }
+}
+
+/// Compares the outputs token by token, where tokens are separated by whitespace, so that the
+/// layout of the output doesn't matter.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokenChecker {
+    /// Compares ASCII letters case-insensitively, e.g. for problems answered with YES or NO.
+    pub ignore_case: bool,
+}
+
+impl Checker for TokenChecker {
+    fn check(&self, _input: &str, expected: &[u8], actual: &[u8]) -> Verdict {
+        check_tokens(expected, actual, |x, y| {
+            let equal = match self.ignore_case {
+                true => x.eq_ignore_ascii_case(y),
+                false => x == y,
+            };
+            (!equal).then(String::new)
+        })
+    }
+}
+
+/// Compares the tokens of the outputs in order. If the tokens at a position differ, `differ`
+/// returns the details of the difference, which may be empty.
+fn check_tokens(
+    expected: &[u8],
+    actual: &[u8],
+    mut differ: impl FnMut(&[u8], &[u8]) -> Option<String>,
+) -> Verdict {
+    let mut expected_tokens = tokens(expected);
+    let mut actual_tokens = tokens(actual);
+    for token in 1.. {
+        match (expected_tokens.next(), actual_tokens.next()) {
+            (Some(x), Some(y)) => {
+                if let Some(details) = differ(x, y) {
+                    let (x, y) = (escape_bytes(x), escape_bytes(y));
+                    return Verdict::WrongAnswer(match details.is_empty() {
+                        true => format!("Token {token} differs: expected {x}, found {y}"),
+                        false => {
+                            format!("Token {token} differs: expected {x}, found {y} ({details})")
+                        }
+                    });
+                }
+            }
+            (None, None) => return Verdict::Accepted,
+            (Some(x), None) => {
+                return Verdict::WrongAnswer(format!(
+                    "Output ends early at token {token}, expected {}",
+                    escape_bytes(x)
+                ));
+            }
+            (None, Some(y)) => {
+                return Verdict::WrongAnswer(format!("Extra token {token}: {}", escape_bytes(y)));
+            }
+        }
+    }
+    unreachable!()
+}
+
+/// Splits an output into tokens separated by ASCII whitespace.
+fn tokens(s: &[u8]) -> impl Iterator<Item = &[u8]> {
+    s.split(u8::is_ascii_whitespace).filter(|x| !x.is_empty())
+}
+
+/// Splits an output into lines, without the line endings.
+fn lines(s: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let s = s.strip_suffix(b"\n").unwrap_or(s);
+    s.split(|&b| b == b'\n')
+        .map(|l| l.strip_suffix(b"\r").unwrap_or(l))
+        .filter(move |_| !s.is_empty())
+}
+
+/// Compares the outputs as multisets of lines or tokens, for problems accepting the answer in any
+/// order. Lines are compared without trailing whitespace, and blank lines at the end are ignored.
+#[derive(Clone, Copy, Debug)]
+pub enum UnorderedChecker {
+    Lines,
+    Tokens,
+}
+
+impl Checker for UnorderedChecker {
+    fn check(&self, _input: &str, expected: &[u8], actual: &[u8]) -> Verdict {
+        let (unit, expected, actual) = match self {
+            UnorderedChecker::Lines => {
+                let (expected, actual) = (process_bytes(expected), process_bytes(actual));
+                let count = |s: &[u8]| count_items(lines(s).map(<[u8]>::to_vec));
+                ("line", count(&expected), count(&actual))
+            }
+            UnorderedChecker::Tokens => {
+                let count = |s: &[u8]| count_items(tokens(s).map(<[u8]>::to_vec));
+                ("token", count(expected), count(actual))
+            }
+        };
+
+        // The items are sorted, so that the same difference is reported every time
+        let mut items: Vec<&Vec<u8>> = expected.keys().chain(actual.keys()).collect();
+        items.sort_unstable();
+        for item in items {
+            let x = expected.get(item).copied().unwrap_or(0);
+            let y = actual.get(item).copied().unwrap_or(0);
+            if x != y {
+                return Verdict::WrongAnswer(format!(
+                    "Expected the {unit} {:?} {x} time(s), found {y} time(s)",
+                    escape_bytes(item)
+                ));
+            }
+        }
+        Verdict::Accepted
+    }
+}
+
+fn count_items(items: impl Iterator<Item = Vec<u8>>) -> HashMap<Vec<u8>, usize> {
+    let mut counts = HashMap::new();
+    for item in items {
+        *counts.entry(item).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Parses a token as a finite number. `inf` and `nan` are left as words.
+fn parse_number(token: &[u8]) -> Option<f64> {
+    std::str::from_utf8(token)
+        .ok()?
+        .parse::<f64>()
+        .ok()
+        .filter(|x| x.is_finite())
+}
+
+/// An external checker following the conventions of testlib. It's run as
+/// `checker <input> <output> <answer>`, and its exit code tells the verdict: 0 for accepted, 1 for
+/// a wrong answer, 2 for a presentation error and 3 for a failure of the checker. What it prints is
+/// taken as the message.
+#[derive(Clone, Debug)]
+pub struct SpecialJudge {
+    /// The language the checker was written in.
+    pub lang: RunLang,
+    /// The checker, as returned by `compile`.
+    pub prog: PathBuf,
+}
+
+impl SpecialJudge {
+    fn run(
+        &self,
+        input: &str,
+        expected: &[u8],
+        actual: &[u8],
+        time_limit: Duration,
+    ) -> Result<Verdict> {
+        let base = PathBuf::from("./compile/temp/").join(random_name());
+        let files = ["input", "output", "answer", "message"].map(|x| base.with_extension(x));
+        let result = (|| {
+            fs::write(&files[0], input)?;
+            fs::write(&files[1], actual)?;
+            fs::write(&files[2], expected)?;
+            let message = fs::File::create(&files[3])?;
+
+            let (program, mut args) = invocation(self.lang, &self.prog);
+            #[cfg(unix)]
+            if self.lang == RunLang::Exec {
+                // Arguments of `sh -c` start from $0, so the files are given from $1
+                args.push("checker".into());
+            }
+            let mut command = Command::new(program);
+            command
+                .args(args)
+                .args(&files[..3])
+                .stdin(Stdio::null())
+                .stdout(message.try_clone()?)
+                .stderr(message);
+            // So that `wait_or_kill` kills what an exec checker spawns too
+            let mut child = own_process_group(&mut command).spawn()?;
+            let status = wait_or_kill(&mut child, Some(time_limit))?;
+            let message = fs::read(&files[3])?;
+            let message = String::from_utf8_lossy(&message).trim().to_owned();
+
+            Ok(match status.map(|x| x.code()) {
+                None => Verdict::Fail(format!(
+                    "The checker ran longer than {}ms",
+                    time_limit.as_millis()
+                )),
+                Some(Some(0)) => Verdict::Accepted,
+                Some(Some(1)) => Verdict::WrongAnswer(message),
+                Some(Some(2)) => Verdict::PresentationError(message),
+                Some(Some(3)) => Verdict::Fail(message),
+                Some(code) => Verdict::Fail(format!(
+                    "The checker exited with {}: {message}",
+                    code.map_or("a signal".to_owned(), |x| format!("code {x}"))
+                )),
+            })
+        })();
+
+        for file in files.iter() {
+            fs::remove_file(file).ok();
+        }
+        result
+    }
+}
+
+impl Checker for SpecialJudge {
+    fn check(&self, input: &str, expected: &[u8], actual: &[u8]) -> Verdict {
+        self.run(input, expected, actual, SPECIAL_JUDGE_TIME_LIMIT)
+            .unwrap_or_else(|e| Verdict::Fail(format!("Failed to run the checker: {e}")))
+    }
+}
+
+/// Compares the outputs byte by byte, like strict judges do. An output differing only in whitespace
+/// is a presentation error, and so is one differing only in line endings unless `crlf` is set.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StrictChecker {
+    /// Converts CRLF line endings to LF before comparing.
+    pub crlf: bool,
+}
+
+impl Checker for StrictChecker {
+    fn check(&self, _input: &str, expected: &[u8], actual: &[u8]) -> Verdict {
+        if expected == actual {
+            return Verdict::Accepted;
+        }
+        let expected = replace_crlf(expected);
+        let actual = replace_crlf(actual);
+        if expected == actual {
+            return match self.crlf {
+                true => Verdict::Accepted,
+                false => Verdict::PresentationError(
+                    "Only line endings differ, check for CRLF (\\r\\n) in either output".to_owned(),
+                ),
+            };
+        }
+
+        match check_tokens(&expected, &actual, |x, y| (x != y).then(String::new)) {
+            Verdict::Accepted => {
+                Verdict::PresentationError(whitespace_difference(&expected, &actual))
+            }
+            verdict => verdict,
+        }
+    }
+}
+
+fn replace_crlf(s: &[u8]) -> Vec<u8> {
+    let mut builder = Vec::with_capacity(s.len());
+    for (i, &b) in s.iter().enumerate() {
+        if !(b == b'\r' && s.get(i + 1) == Some(&b'\n')) {
+            builder.push(b);
+        }
+    }
+    builder
+}
+
+/// Describes where two outputs with the same tokens start to differ.
+fn whitespace_difference(expected: &[u8], actual: &[u8]) -> String {
+    let at = expected
+        .iter()
+        .zip(actual)
+        .position(|(x, y)| x != y)
+        .unwrap_or(expected.len().min(actual.len()));
+    let line = expected[..at].iter().filter(|&&b| b == b'\n').count() + 1;
+    let line_start = expected[..at]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |x| x + 1);
+    let column = String::from_utf8_lossy(&expected[line_start..at])
+        .chars()
+        .count()
+        + 1;
+    // Only whitespace differs, which is always a single byte
+    let describe = |s: &[u8]| match s.get(at) {
+        Some(&b) => format!("'{}'", b.escape_ascii()),
+        None => "the end of the output".to_owned(),
+    };
+    format!(
+        "Only whitespace differs, first at line {line}, column {column}: expected {}, found {}",
+        describe(expected),
+        describe(actual)
+    )
+}
+
+/// The names of the checkers that can be selected with `checker_by_name`.
+pub const CHECKER_NAMES: [&str; 7] = [
+    "lines",
+    "strict",
+    "tokens",
+    "nocase",
+    "unordered-lines",
+    "unordered-tokens",
+    "float",
+];
+
+/// Returns the built-in checker of the given name.
+pub fn checker_by_name(name: &str) -> Result<Box<dyn Checker>> {
+    match name {
+        "lines" => Ok(Box::new(LineChecker)),
+        "strict" => Ok(Box::new(StrictChecker::default())),
+        "tokens" => Ok(Box::new(TokenChecker::default())),
+        "nocase" => Ok(Box::new(TokenChecker { ignore_case: true })),
+        "unordered-lines" => Ok(Box::new(UnorderedChecker::Lines)),
+        "unordered-tokens" => Ok(Box::new(UnorderedChecker::Tokens)),
+        "float" => Ok(Box::new(FloatChecker::default())),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Unknown checker: {name} (expected one of {})",
+                CHECKER_NAMES.join(", ")
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{
+        Checker, FloatChecker, LineChecker, SpecialJudge, StrictChecker, TokenChecker, Tokens,
+        UnorderedChecker, Verdict,
+    };
+    use crate::core::run_code::{exec_fixture, ExecFixture};
+
+    impl From<ExecFixture> for SpecialJudge {
+        fn from(x: ExecFixture) -> Self {
+            Self {
+                lang: x.lang,
+                prog: x.prog,
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn special_judge() {
+        let judge = exec_fixture::<SpecialJudge>;
+        let compare =
+            judge(r#"[ "$(cat "$2")" = "$(cat "$3")" ] || { echo "$0 differs"; exit 1; }"#);
+        assert!(compare.check("1", b"2\n", b"2\n").is_accepted());
+        assert_eq!(
+            compare.check("1", b"2\n", b"3\n"),
+            Verdict::WrongAnswer("checker differs".to_owned())
+        );
+
+        let exit = |code| judge(&format!("echo message; exit {code}")).check("", b"", b"");
+        assert_eq!(exit(0), Verdict::Accepted);
+        assert_eq!(exit(2), Verdict::PresentationError("message".to_owned()));
+        assert_eq!(exit(3), Verdict::Fail("message".to_owned()));
+        assert_eq!(
+            exit(7),
+            Verdict::Fail("The checker exited with code 7: message".to_owned())
+        );
+
+        let slow = judge("sleep 5").run("", b"", b"", Duration::from_millis(100));
+        assert_eq!(
+            slow.unwrap(),
+            Verdict::Fail("The checker ran longer than 100ms".to_owned())
+        );
+    }
+
+    #[test]
+    fn line_checker() {
+        let checker = LineChecker;
+        assert!(checker
+            .check("", b"1 2\n3\n", b"1 2  \n3\n\n\n")
+            .is_accepted());
+        assert_eq!(
+            checker.check("", b"1 2\n3\n", b"1 2\n4\n"),
+            Verdict::WrongAnswer("Line 2 differs".to_owned())
+        );
+        assert!(!checker.check("", b"1\n2\n", b"1\n").is_accepted());
+        assert!(!checker.check("", b"1\n", b"1\n2\n").is_accepted());
+    }
+
+    #[test]
+    fn float_checker() {
+        let checker = FloatChecker::default();
+        assert!(checker
+            .check("", b"0.5 YES\n", b"0.5000000\nYES")
+            .is_accepted());
+        assert!(checker.check("", b"1000000", b"1000000.5").is_accepted());
+        assert!(checker.check("", b"0", b"-0.0000001").is_accepted());
+        assert!(!checker.check("", b"0.5", b"0.5001").is_accepted());
+        assert!(!checker.check("", b"YES", b"yes").is_accepted());
+        assert!(!checker.check("", b"1 2", b"1").is_accepted());
+        assert!(!checker.check("", b"nan", b"1").is_accepted());
+        assert_eq!(
+            checker.check("", b"1 2", b"1 3"),
+            Verdict::WrongAnswer(
+                "Token 2 differs: expected 2, found 3 (absolute error 1e0, relative error 5e-1)"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn strict_checker() {
+        let checker = StrictChecker::default();
+        assert!(checker.check("", b"1 2\n", b"1 2\n").is_accepted());
+        assert_eq!(
+            checker.check("", b"1 2\n3\n", b"1 2\n3 \n"),
+            Verdict::PresentationError(
+                "Only whitespace differs, first at line 2, column 2: expected '\\n', found ' '"
+                    .to_owned()
+            )
+        );
+        assert!(matches!(
+            checker.check("", b"1\n", b"1"),
+            Verdict::PresentationError(_)
+        ));
+        assert!(matches!(
+            checker.check("", b"1\n2\n", b"1\r\n2\r\n"),
+            Verdict::PresentationError(_)
+        ));
+        assert!(matches!(
+            checker.check("", b"1\n", b"2\n"),
+            Verdict::WrongAnswer(_)
+        ));
+        let checker = StrictChecker { crlf: true };
+        assert!(checker.check("", b"1\n2\n", b"1\r\n2\r\n").is_accepted());
+    }
+
+    #[test]
+    fn token_checkers() {
+        let checker = TokenChecker::default();
+        assert!(checker.check("", b"1 2\n3", b"1\n2 3\n").is_accepted());
+        assert!(!checker.check("", b"YES", b"yes").is_accepted());
+        let checker = TokenChecker { ignore_case: true };
+        assert!(checker.check("", b"YES\nNo", b"yes NO").is_accepted());
+        assert!(!checker.check("", b"YES", b"YES NO").is_accepted());
+    }
+
+    #[test]
+    fn unordered_checkers() {
+        let checker = UnorderedChecker::Lines;
+        assert!(checker
+            .check("", b"1 2\n3 4\n", b"3 4  \n1 2\n\n")
+            .is_accepted());
+        assert!(!checker
+            .check("", b"1 2\n3 4\n", b"2 1\n3 4\n")
+            .is_accepted());
+        let checker = UnorderedChecker::Tokens;
+        assert!(checker.check("", b"1 2\n3 2\n", b"2 3 2 1").is_accepted());
+        assert_eq!(
+            checker.check("", b"1 2 2", b"1 1 2"),
+            Verdict::WrongAnswer("Expected the token \"1\" 1 time(s), found 2 time(s)".to_owned())
+        );
+    }
+
+    #[test]
+    fn read_tokens() {
+        let mut tokens = Tokens::output("3\n1 2 x\n");
+        assert_eq!(tokens.read::<usize>(), Ok(3));
+        assert_eq!(tokens.read_vec::<i32>(2), Ok(vec![1, 2]));
+        assert_eq!(
+            tokens.read::<i32>(),
+            Err(Verdict::WrongAnswer(
+                "Expected i32 at token 4, found x".to_owned()
+            ))
+        );
+        assert!(tokens.expect_end().is_ok());
+        assert!(matches!(Tokens::jury("").token(), Err(Verdict::Fail(_))));
+    }
+
+    #[test]
+    fn closure_checker() {
+        let checker = |_: &str, _: &str, actual: &str| match actual.trim() {
+            "YES" => Verdict::Accepted,
+            _ => Verdict::WrongAnswer("Expected YES".to_owned()),
+        };
+        assert!(checker.check("", b"", b"YES\n").is_accepted());
+        assert!(!checker.check("", b"YES", b"NO").is_accepted());
+        assert_eq!(
+            checker.check("", b"YES", b"YE\xff"),
+            Verdict::WrongAnswer("The output isn't valid UTF-8 from byte 2".to_owned())
+        );
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        assert!(LineChecker
+            .check("", b"\xff 1\n", b"\xff 1  \n")
+            .is_accepted());
+        assert!(!LineChecker.check("", b"\xff\n", b"\xfe\n").is_accepted());
+        assert_eq!(
+            TokenChecker::default().check("", b"1 \xff", b"1 \xfe"),
+            Verdict::WrongAnswer("Token 2 differs: expected \\xff, found \\xfe".to_owned())
+        );
+    }
+}
@@ -1,18 +1,110 @@
 use std::{
+    collections::HashMap,
     env,
-    io::{self, Result},
+    ffi::OsString,
+    fs,
+    io::{self, Read, Result, Write},
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, SystemTime},
 };
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+use serde::Deserialize;
+
+use crate::core::run_code::{own_process_group, random_name, wait_or_kill};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum RunLang {
     C,
     Cpp,
     Python,
+    PyPy,
     Java,
     Go,
     Rust,
+    Kotlin,
+    Scala,
+    CSharp,
+    JavaScript,
+    Ruby,
+    Haskell,
+    OCaml,
+    /// An arbitrary command line, run through the shell without being compiled.
+    Exec,
+}
+
+impl RunLang {
+    /// Every supported language with a toolchain, which excludes `Exec`.
+    pub const ALL: [RunLang; 14] = [
+        RunLang::C,
+        RunLang::Cpp,
+        RunLang::Python,
+        RunLang::PyPy,
+        RunLang::Java,
+        RunLang::Go,
+        RunLang::Rust,
+        RunLang::Kotlin,
+        RunLang::Scala,
+        RunLang::CSharp,
+        RunLang::JavaScript,
+        RunLang::Ruby,
+        RunLang::Haskell,
+        RunLang::OCaml,
+    ];
+
+    /// Returns the canonical name of the language, as accepted on the command line.
+    pub fn name(self) -> &'static str {
+        use RunLang::*;
+        match self {
+            C => "c",
+            Cpp => "cpp",
+            Python => "python",
+            PyPy => "pypy",
+            Java => "java",
+            Go => "go",
+            Rust => "rust",
+            Kotlin => "kotlin",
+            Scala => "scala",
+            CSharp => "csharp",
+            JavaScript => "javascript",
+            Ruby => "ruby",
+            Haskell => "haskell",
+            OCaml => "ocaml",
+            Exec => "exec",
+        }
+    }
+
+    /// Returns the name the code should have in its directory, or None if there's no code.
+    pub fn code_file(self) -> Option<&'static str> {
+        use RunLang::*;
+        Some(match self {
+            C => "main.c",
+            Cpp => "main.cpp",
+            Python | PyPy => "main.py",
+            Java => "Main.java",
+            Go => "main.go",
+            Rust => "main.rs",
+            Kotlin => "main.kt",
+            Scala => "main.scala",
+            CSharp => "main.cs",
+            JavaScript => "main.js",
+            Ruby => "main.rb",
+            Haskell => "main.hs",
+            OCaml => "main.ml",
+            Exec => return None,
+        })
+    }
+
+    /// Guesses the language of a code from the extension of its path. A `.py` code is Python.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<RunLang> {
+        let extension = path.as_ref().extension()?;
+        RunLang::ALL.into_iter().find(|lang| {
+            lang.code_file()
+                .and_then(|file| Path::new(file).extension())
+                .is_some_and(|x| x == extension)
+        })
+    }
 }
 
 impl TryFrom<&str> for RunLang {
@@ -23,21 +115,199 @@ impl TryFrom<&str> for RunLang {
         match value {
             "c" => Ok(C),
             "cpp" | "c++" => Ok(Cpp),
-            "py" | "python" | "pypy" => Ok(Python),
+            "py" | "python" => Ok(Python),
+            "pypy" | "pypy3" => Ok(PyPy),
             "java" => Ok(Java),
             "go" | "golang" => Ok(Go),
             "rust" | "rs" => Ok(Rust),
+            "kotlin" | "kt" => Ok(Kotlin),
+            "scala" => Ok(Scala),
+            "csharp" | "cs" | "c#" => Ok(CSharp),
+            "javascript" | "js" | "node" => Ok(JavaScript),
+            "ruby" | "rb" => Ok(Ruby),
+            "haskell" | "hs" => Ok(Haskell),
+            "ocaml" | "ml" => Ok(OCaml),
+            "exec" => Ok(Exec),
             _ => Err(io::Error::new(InvalidInput, "Wrong language name")),
         }
     }
 }
 
+const PCH_HEADER: &str = "bits/stdc++.h";
+
+const BYTE_COMPILE_SCRIPT: &str =
+    "import sys; compile(open(sys.argv[1], encoding='utf-8').read(), sys.argv[1], 'exec')";
+
+/// Compiler flags of a single language.
+#[derive(Clone, Default, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LangFlags {
+    /// Language standard or edition, e.g. `gnu++20` for C++ or `2018` for Rust.
+    pub std: Option<String>,
+    /// Replaces the default flags of the language if set.
+    pub flags: Option<Vec<String>>,
+    /// Flags appended after the other ones, e.g. `-DLOCAL` or `-march=native`.
+    pub extra: Vec<String>,
+}
+
+/// The kind of build `compile_with` produces.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Profile {
+    /// Optimized build with the configured flags.
+    #[default]
+    Release,
+    /// Build with sanitizers, debug assertions and overflow checks, for diagnosing failures.
+    /// The language standard and extra flags still apply, but replaced flags are ignored.
+    Debug,
+}
+
+impl Profile {
+    /// Returns whether the debug profile adds any runtime checks for `lang`.
+    pub fn has_checks(lang: RunLang) -> bool {
+        matches!(lang, RunLang::C | RunLang::Cpp | RunLang::Rust)
+    }
+}
+
+/// Limits on a single step of compilation, e.g. a run of the compiler.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CompileLimits {
+    /// The compiler is killed if it runs longer than this.
+    pub time: Option<Duration>,
+    /// The address space of the compiler in bytes. Only applied on unix.
+    pub memory: Option<u64>,
+}
+
+/// Per-language compiler flag overrides used by `compile_with`.
+#[derive(Clone, Default, Debug)]
+pub struct CompileOptions {
+    pub flags: HashMap<RunLang, LangFlags>,
+    pub profile: Profile,
+    /// Byte-compiles Python codes to catch syntax errors before they're run.
+    pub byte_compile: bool,
+    /// Crates single-file Rust codes are compiled against, either as a directory of prebuilt
+    /// rlibs or as a vendored Cargo workspace, which is built offline first.
+    pub rust_deps: Option<PathBuf>,
+    pub limits: CompileLimits,
+    /// Directory where build artifacts shared between compilations, such as precompiled headers,
+    /// are kept. Nothing is cached if not set.
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl CompileOptions {
+    /// Sets the language standard or edition of `lang`.
+    pub fn set_std(&mut self, lang: RunLang, std: impl Into<String>) {
+        self.flags.entry(lang).or_default().std = Some(std.into());
+    }
+
+    /// Appends an extra flag to the compiler invocation of `lang`.
+    pub fn push_flag(&mut self, lang: RunLang, flag: impl Into<String>) {
+        self.flags.entry(lang).or_default().extra.push(flag.into());
+    }
+
+    /// Returns the flags of `lang` that come before the language standard and the extra flags,
+    /// which are either the replaced flags or the defaults of the profile.
+    pub fn base_args(&self, lang: RunLang) -> Vec<String> {
+        use RunLang::*;
+        let replaced = self.flags.get(&lang).and_then(|x| x.flags.as_ref());
+
+        match (replaced, self.profile) {
+            (Some(x), Profile::Release) => x.clone(),
+            (_, profile) => match (lang, profile) {
+                (C | Cpp, Profile::Release) => vec!["-O2", "-Wall", "-lm", "-static"],
+                (C | Cpp, Profile::Debug) => vec![
+                    "-O1",
+                    "-g",
+                    "-Wall",
+                    "-lm",
+                    "-fsanitize=address,undefined",
+                    "-fno-omit-frame-pointer",
+                ],
+                (Java, _) => vec!["-encoding", "utf-8"],
+                (Rust, Profile::Release) => vec!["-O"],
+                (Rust, Profile::Debug) => vec![
+                    "-O",
+                    "-g",
+                    "-C",
+                    "debug-assertions=on",
+                    "-C",
+                    "overflow-checks=on",
+                ],
+                (Haskell, _) => vec!["-O2"],
+                (
+                    Python | PyPy | Go | Kotlin | Scala | CSharp | JavaScript | Ruby | OCaml | Exec,
+                    _,
+                ) => vec![],
+            }
+            .into_iter()
+            .map(str::to_owned)
+            .collect(),
+        }
+    }
+
+    /// Returns the flags passed to the compiler of `lang`, excluding the source and output paths.
+    pub fn args(&self, lang: RunLang) -> Vec<String> {
+        use RunLang::*;
+        let default = LangFlags::default();
+        let flags = self.flags.get(&lang).unwrap_or(&default);
+        let mut args = self.base_args(lang);
+
+        let std = flags.std.as_deref().or(match lang {
+            C => Some("gnu11"),
+            Cpp => Some("gnu++17"),
+            Rust => Some("2021"),
+            Python | PyPy | Java | Go | Kotlin | Scala | CSharp | JavaScript | Ruby | Haskell
+            | OCaml | Exec => None,
+        });
+        if let Some(std) = std {
+            match lang {
+                C | Cpp => args.push(format!("-std={std}")),
+                Java => args.extend(["--release".to_owned(), std.to_owned()]),
+                Rust => args.extend(["--edition".to_owned(), std.to_owned()]),
+                Python | PyPy | Go | Kotlin | Scala | CSharp | JavaScript | Ruby | Haskell
+                | OCaml | Exec => {}
+            }
+        }
+
+        args.extend(flags.extra.iter().cloned());
+        args
+    }
+}
+
+/// Compiles the code with the default flags of each language.
 pub fn compile(
     lang: RunLang,
     code_path: impl AsRef<Path>,
     exec_dir: impl AsRef<Path>,
     exec_name: impl AsRef<Path>,
 ) -> Result<PathBuf> {
+    compile_with(
+        lang,
+        code_path,
+        exec_dir,
+        exec_name,
+        &CompileOptions::default(),
+    )
+}
+
+fn exec_not_compiled() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "exec commands are run as they are, without compilation",
+    )
+}
+
+/// Compiles the code with the flags given in `options`.
+pub fn compile_with(
+    lang: RunLang,
+    code_path: impl AsRef<Path>,
+    exec_dir: impl AsRef<Path>,
+    exec_name: impl AsRef<Path>,
+    options: &CompileOptions,
+) -> Result<PathBuf> {
+    if lang == RunLang::Exec {
+        return Err(exec_not_compiled());
+    }
+
     let code_path = absolute_path(code_path)?;
     let exec_dir = absolute_path(exec_dir)?;
 
@@ -45,84 +315,168 @@ pub fn compile(
         let mut exec_path = exec_dir;
         exec_path.push(&exec_name);
         exec_path.set_extension(match lang {
-            RunLang::Python => "py",
-            RunLang::Java => "jar",
+            RunLang::Python | RunLang::PyPy => "py",
+            RunLang::JavaScript => "js",
+            RunLang::Ruby => "rb",
+            RunLang::Java | RunLang::Kotlin | RunLang::Scala => "jar",
             _ => "exe",
         });
         exec_path
     };
 
+    let args = options.args(lang);
+
+    // A stale executable from an earlier run shouldn't be mistaken for a successful build
+    if exec_path.exists() {
+        fs::remove_file(&exec_path)?;
+    }
+
     // Compilation can run asynchronously
-    match lang {
-        RunLang::C => {
-            Command::new("gcc")
-                .arg(&code_path)
-                .arg("-o")
-                .arg(&exec_path)
-                .args(["-O2", "-Wall", "-lm", "-static", "-std=gnu11"])
-                .spawn()
-                .and_then(|mut x| x.wait())
-                .expect("Failed to execute compilation");
-        }
-        RunLang::Cpp => {
-            Command::new("g++")
-                .arg(&code_path)
-                .arg("-o")
-                .arg(&exec_path)
-                .args(["-O2", "-Wall", "-lm", "-static", "-std=gnu++17"])
-                .spawn()
-                .and_then(|mut x| x.wait())
-                .expect("Failed to execute compilation");
-        }
-        RunLang::Python => {
-            Command::new("cp")
-                .arg(&code_path)
-                .arg(&exec_path)
-                .spawn()
-                .and_then(|mut x| x.wait())
-                .expect("Failed to execute compilation");
-        }
-        RunLang::Java => {
-            let mut code_dir = code_path.clone();
-            code_dir.pop();
-            let code_dir = code_dir.to_str().unwrap();
+    if code_path.is_dir() {
+        build_project(lang, &code_path, &exec_path, options)?;
+    } else {
+        match lang {
+            RunLang::C => {
+                run_step(
+                    &options.limits,
+                    Command::new("gcc")
+                        .arg(&code_path)
+                        .arg("-o")
+                        .arg(&exec_path)
+                        .args(&args),
+                )?;
+            }
+            RunLang::Cpp => {
+                // GCC silently ignores the precompiled header if it doesn't match the code
+                let pch_dir = match &options.cache_dir {
+                    Some(cache_dir) if fs::read_to_string(&code_path)?.contains(PCH_HEADER) => {
+                        precompiled_header(&absolute_path(cache_dir)?, &args, &options.limits)
+                    }
+                    _ => None,
+                };
+                let pch_args = pch_dir.map(|x| vec!["-I".into(), x.into_os_string()]);
 
-            Command::new("javac")
-                .args(["-encoding", "utf-8"])
-                .arg(&code_path)
-                .spawn()
-                .and_then(|mut x| x.wait())
-                .expect("Failed to execute compilation");
-
-            let exec_path = absolute_path(&exec_path)?;
-            let exec_path = exec_path.to_str().unwrap();
-            Command::new("bash")
-                .arg("-c")
-                .arg(format!(
-                    "cd {code_dir} && jar -cf {exec_path} *.class && rm *.class"
-                ))
-                .spawn()
-                .and_then(|mut x| x.wait())
-                .expect("Failed to execute compilation");
-        }
-        RunLang::Go => {
-            Command::new("go")
-                .arg("build")
-                .arg("-o")
-                .arg(&exec_path)
-                .arg(&code_path)
-                .spawn()
-                .and_then(|mut x| x.wait())
-                .expect("Failed to execute compilation");
-        }
-        RunLang::Rust => {
-            Command::new("rustc")
-                .args(["--edition", "2021", "-O", "-o"])
-                .arg(&exec_path)
-                .arg(&code_path)
-                .spawn()
-                .and_then(|mut x| x.wait())
-                .expect("Failed to execute compilation");
+                run_step(
+                    &options.limits,
+                    Command::new("g++")
+                        .args(pch_args.unwrap_or_default())
+                        .arg(&code_path)
+                        .arg("-o")
+                        .arg(&exec_path)
+                        .args(&args),
+                )?;
+            }
+            RunLang::Python | RunLang::PyPy => {
+                if options.byte_compile {
+                    // Only a syntax check; the bytecode isn't written anywhere
+                    let interpreter = if lang == RunLang::PyPy {
+                        "pypy3"
+                    } else {
+                        "python3"
+                    };
+                    run_step(
+                        &options.limits,
+                        Command::new(interpreter)
+                            .args(["-c", BYTE_COMPILE_SCRIPT])
+                            .arg(&code_path),
+                    )?;
+                }
+                run_step(
+                    &options.limits,
+                    Command::new("cp").arg(&code_path).arg(&exec_path),
+                )?;
+            }
+            RunLang::Java => {
+                build_java(&code_path, &exec_path, &args, &options.limits)?;
+            }
+            RunLang::Kotlin => {
+                // The runtime is bundled, so that the jar runs on a plain JVM
+                run_step(
+                    &options.limits,
+                    Command::new("kotlinc")
+                        .arg(&code_path)
+                        .arg("-include-runtime")
+                        .args(&args)
+                        .arg("-d")
+                        .arg(&exec_path),
+                )?;
+            }
+            RunLang::Scala => {
+                run_step(
+                    &options.limits,
+                    Command::new("scalac")
+                        .args(&args)
+                        .arg("-d")
+                        .arg(&exec_path)
+                        .arg(&code_path),
+                )?;
+            }
+            RunLang::CSharp => {
+                build_csharp(&code_path, &exec_path, &args, &options.limits)?;
+            }
+            RunLang::JavaScript | RunLang::Ruby => {
+                run_step(
+                    &options.limits,
+                    Command::new("cp").arg(&code_path).arg(&exec_path),
+                )?;
+            }
+            RunLang::Haskell => {
+                // Intermediate files are kept out of the code directory
+                with_temp_dir("haskell", |out_dir| {
+                    run_step(
+                        &options.limits,
+                        Command::new("ghc")
+                            .args(&args)
+                            .arg("-outputdir")
+                            .arg(out_dir)
+                            .arg("-o")
+                            .arg(&exec_path)
+                            .arg(&code_path),
+                    )
+                })?;
+            }
+            RunLang::OCaml => {
+                // ocamlopt writes intermediate files next to the code, so a copy is compiled
+                with_temp_dir("ocaml", |out_dir| {
+                    let copied = out_dir.join("main.ml");
+                    fs::copy(&code_path, &copied)?;
+                    run_step(
+                        &options.limits,
+                        Command::new("ocamlopt")
+                            .args(&args)
+                            .arg("-o")
+                            .arg(&exec_path)
+                            .arg(copied),
+                    )
+                })?;
+            }
+            RunLang::Go => {
+                run_step(
+                    &options.limits,
+                    Command::new("go")
+                        .arg("build")
+                        .args(&args)
+                        .arg("-o")
+                        .arg(&exec_path)
+                        .arg(&code_path),
+                )?;
+            }
+            RunLang::Rust => {
+                let dep_args = match &options.rust_deps {
+                    Some(deps) => rust_dep_args(&absolute_path(deps)?, &options.limits)?,
+                    None => vec![],
+                };
+                run_step(
+                    &options.limits,
+                    Command::new("rustc")
+                        .args(&args)
+                        .args(dep_args)
+                        .arg("-o")
+                        .arg(&exec_path)
+                        .arg(&code_path),
+                )?;
+            }
+            RunLang::Exec => return Err(exec_not_compiled()),
         }
     }
 
@@ -136,6 +490,571 @@ pub fn compile(
     }
 }
 
+/// The file marking a directory of C or C++ sources to be compiled together.
+pub const PROJECT_MARKER: &str = ".project";
+
+/// Returns whether `dir` holds a project of several files in `lang` rather than a single
+/// `main.<ext>`: a Cargo package for Rust, a module for Go, or sources for C and C++ marked with
+/// `PROJECT_MARKER`, so that stray sources next to `main.<ext>` are still ignored.
+pub fn is_project(lang: RunLang, dir: impl AsRef<Path>) -> bool {
+    let dir = dir.as_ref();
+    match lang {
+        RunLang::Rust => dir.join("Cargo.toml").is_file(),
+        RunLang::Go => dir.join("go.mod").is_file(),
+        RunLang::C | RunLang::Cpp => dir.join(PROJECT_MARKER).is_file(),
+        _ => false,
+    }
+}
+
+fn source_extensions(lang: RunLang) -> &'static [&'static str] {
+    match lang {
+        RunLang::C => &["c"],
+        RunLang::Cpp => &["cpp", "cc", "cxx"],
+        _ => &[],
+    }
+}
+
+/// Builds a project directory, and copies the resulting executable to `exec_path`.
+fn build_project(
+    lang: RunLang,
+    project_dir: &Path,
+    exec_path: &Path,
+    options: &CompileOptions,
+) -> Result<()> {
+    match lang {
+        RunLang::C | RunLang::Cpp => {
+            let mut sources = Vec::new();
+            for extension in source_extensions(lang) {
+                collect_files(project_dir, extension, &mut sources)?;
+            }
+            sources.sort();
+            run_step(
+                &options.limits,
+                Command::new(if lang == RunLang::C { "gcc" } else { "g++" })
+                    .args(&sources)
+                    .arg("-I")
+                    .arg(project_dir)
+                    .arg("-o")
+                    .arg(exec_path)
+                    .args(options.args(lang)),
+            )
+        }
+        RunLang::Go => run_step(
+            &options.limits,
+            Command::new("go")
+                .arg("build")
+                .args(options.args(lang))
+                .arg("-o")
+                .arg(exec_path)
+                .arg(".")
+                .current_dir(project_dir),
+        ),
+        RunLang::Rust => {
+            let manifest_path = project_dir.join("Cargo.toml");
+            let bin_name = cargo_bin_name(&fs::read_to_string(&manifest_path)?)?;
+
+            // Only the extra flags apply, since cargo decides the rest from its profile
+            let extra = options
+                .flags
+                .get(&lang)
+                .map(|x| x.extra.join("\x1f"))
+                .unwrap_or_default();
+            let (profile_arg, profile_dir) = match options.profile {
+                Profile::Release => (Some("--release"), "release"),
+                Profile::Debug => (None, "debug"),
+            };
+            // Given explicitly, since CARGO_TARGET_DIR or a workspace would move it elsewhere
+            let target_dir = project_dir.join("target");
+            run_step(
+                &options.limits,
+                Command::new("cargo")
+                    .arg("build")
+                    .args(profile_arg)
+                    .arg("--manifest-path")
+                    .arg(&manifest_path)
+                    .arg("--target-dir")
+                    .arg(&target_dir)
+                    .env("CARGO_ENCODED_RUSTFLAGS", extra),
+            )?;
+
+            let mut built = target_dir.join(profile_dir).join(bin_name);
+            built.set_extension(env::consts::EXE_EXTENSION);
+            fs::copy(built, exec_path).map(drop)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Projects of {} aren't supported", lang.name()),
+        )),
+    }
+}
+
+/// Returns the rustc arguments making every crate in `deps` available to `extern`. A vendored
+/// workspace is built within the limits of compilation.
+fn rust_dep_args(deps: &Path, limits: &CompileLimits) -> Result<Vec<OsString>> {
+    let lib_dir = if deps.join("Cargo.toml").is_file() {
+        // Given explicitly, since CARGO_TARGET_DIR would move it elsewhere
+        let target_dir = deps.join("target");
+        run_step(
+            limits,
+            Command::new("cargo")
+                .args(["build", "--release", "--offline", "--manifest-path"])
+                .arg(deps.join("Cargo.toml"))
+                .arg("--target-dir")
+                .arg(&target_dir),
+        )?;
+        target_dir.join("release").join("deps")
+    } else {
+        deps.to_path_buf()
+    };
+
+    // The most recently built file wins if a crate was built several times
+    let mut crates: HashMap<String, (SystemTime, PathBuf)> = HashMap::new();
+    for entry in fs::read_dir(&lib_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_lib = path
+            .extension()
+            .is_some_and(|x| x == "rlib" || x == env::consts::DLL_EXTENSION);
+        let name = path
+            .file_stem()
+            .and_then(|x| x.to_str())
+            .and_then(crate_name);
+        if let (true, Some(name)) = (is_lib, name) {
+            let modified = entry.metadata()?.modified()?;
+            if crates.get(&name).is_none_or(|(x, _)| *x < modified) {
+                crates.insert(name, (modified, path));
+            }
+        }
+    }
+
+    let mut dependency = OsString::from("dependency=");
+    dependency.push(&lib_dir);
+    let mut args = vec!["-L".into(), dependency];
+    for (name, (_, path)) in crates {
+        let mut arg = OsString::from(format!("{name}="));
+        arg.push(path);
+        args.extend(["--extern".into(), arg]);
+    }
+    Ok(args)
+}
+
+/// Extracts the crate name from a library file stem such as `libitertools-0123abcd`.
+fn crate_name(stem: &str) -> Option<String> {
+    let name = stem.strip_prefix("lib")?;
+    let name = match name.rsplit_once('-') {
+        Some((name, hash)) if hash.chars().all(|c| c.is_ascii_hexdigit()) => name,
+        _ => name,
+    };
+    Some(name.to_owned())
+}
+
+/// Finds the name of the executable a Cargo manifest builds: the first `[[bin]]` target if
+/// there is one, or the package name otherwise.
+fn cargo_bin_name(manifest: &str) -> Result<String> {
+    let manifest: toml::Value =
+        toml::from_str(manifest).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    manifest
+        .get("bin")
+        .and_then(|x| x.get(0))
+        .and_then(|x| x.get("name"))
+        .or_else(|| manifest.get("package").and_then(|x| x.get("name")))
+        .and_then(|x| x.as_str())
+        .map(str::to_owned)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Cargo.toml doesn't name any executable",
+            )
+        })
+}
+
+/// Runs a step of the compilation and waits for it to finish successfully within the limits.
+fn run_step(limits: &CompileLimits, command: &mut Command) -> Result<()> {
+    let program = command.get_program().to_owned();
+
+    #[cfg(unix)]
+    if let Some(memory) = limits.memory {
+        use std::os::unix::process::CommandExt;
+        // SAFETY: setrlimit is async-signal-safe, and nothing else is done in the forked child
+        unsafe {
+            command.pre_exec(move || {
+                let limit = libc::rlimit {
+                    rlim_cur: memory as libc::rlim_t,
+                    rlim_max: memory as libc::rlim_t,
+                };
+                match libc::setrlimit(libc::RLIMIT_AS, &limit) {
+                    0 => Ok(()),
+                    _ => Err(io::Error::last_os_error()),
+                }
+            });
+        }
+    }
+
+    // A group of its own lets the compiler be killed along with the processes it spawns
+    let mut child = own_process_group(command)
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to execute {program:?}: {e}")))?;
+
+    // The messages are still shown, but kept to tell running out of memory from other failures
+    let mut child_stderr = child.stderr.take().unwrap();
+    let stderr = thread::spawn(move || {
+        let mut buf = Vec::new();
+        child_stderr.read_to_end(&mut buf).ok();
+        io::stderr().write_all(&buf).ok();
+        String::from_utf8_lossy(&buf).into_owned()
+    });
+
+    let status = wait_or_kill(&mut child, limits.time)?;
+    let stderr = stderr.join().unwrap_or_default();
+
+    match status {
+        None => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!(
+                "Compilation limit exceeded: {program:?} ran longer than {}ms",
+                limits.time.unwrap_or_default().as_millis()
+            ),
+        )),
+        Some(status) if status.success() => Ok(()),
+        Some(_) if limits.memory.is_some() && is_out_of_memory(&stderr) => Err(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            format!(
+                "Compilation limit exceeded: {program:?} ran out of {}MiB of memory",
+                limits.memory.unwrap() >> 20
+            ),
+        )),
+        Some(status) => Err(io::Error::other(format!(
+            "{program:?} exited with {status}"
+        ))),
+    }
+}
+
+/// Returns whether the messages of a compiler tell that it couldn't allocate memory.
+fn is_out_of_memory(stderr: &str) -> bool {
+    const MARKERS: [&str; 7] = [
+        "memory exhausted",
+        "out of memory",
+        "memory allocation of",
+        "std::bad_alloc",
+        "Cannot allocate memory",
+        "Could not reserve enough space",
+        "failed to map segment",
+    ];
+    MARKERS.iter().any(|x| stderr.contains(x))
+}
+
+/// Compiles a Java source in a private directory, and packs the classes into a jar whose manifest
+/// points to the class with the `main` method, so that it can be run with `java -jar`.
+fn build_java(
+    code_path: &Path,
+    exec_path: &Path,
+    args: &[String],
+    limits: &CompileLimits,
+) -> Result<()> {
+    with_temp_dir("java", |class_dir| {
+        let mut source_dir = code_path.to_path_buf();
+        source_dir.pop();
+
+        // javac requires the file to be named after its public class
+        let source = fs::read_to_string(code_path)?;
+        let source_path = match find_public_class(&source) {
+            Some(name) => {
+                let source_path = class_dir.join("src").join(format!("{name}.java"));
+                fs::create_dir_all(class_dir.join("src"))?;
+                fs::write(&source_path, source)?;
+                source_path
+            }
+            None => code_path.to_path_buf(),
+        };
+
+        let out_dir = class_dir.join("classes");
+        run_step(
+            limits,
+            Command::new("javac")
+                .args(args)
+                .arg("-d")
+                .arg(&out_dir)
+                .arg("-sourcepath")
+                .arg(&source_dir)
+                .arg(source_path),
+        )?;
+
+        let mut class_files = Vec::new();
+        collect_files(&out_dir, "class", &mut class_files)?;
+        let javap = Command::new("javap").args(&class_files).output()?;
+        let main_class =
+            find_main_class(&String::from_utf8_lossy(&javap.stdout)).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "No class with a main method was found",
+                )
+            })?;
+
+        run_step(
+            limits,
+            Command::new("jar")
+                .arg("cfe")
+                .arg(exec_path)
+                .arg(main_class)
+                .arg("-C")
+                .arg(&out_dir)
+                .arg("."),
+        )
+    })
+}
+
+/// Returns a directory holding `bits/stdc++.h.gch` precompiled with the given flags, building it
+/// in the cache directory if it's not there yet. Returns None if it can't be built.
+fn precompiled_header(
+    cache_dir: &Path,
+    args: &[String],
+    limits: &CompileLimits,
+) -> Option<PathBuf> {
+    // Linker flags are irrelevant to the header, and only cause warnings
+    let args: Vec<&String> = args
+        .iter()
+        .filter(|x| !x.starts_with("-l") && *x != "-static" && *x != "-s")
+        .collect();
+
+    // The header is rebuilt for another compiler, e.g. after an upgrade of g++, since a stale one
+    // would be silently ignored
+    let version = Command::new("g++").arg("--version").output().ok()?.stdout;
+    let version = String::from_utf8_lossy(&version);
+    let mut key = vec![version.lines().next().unwrap_or_default()];
+    key.extend(args.iter().map(|x| x.as_str()));
+    let pch_dir = cache_dir.join(format!("pch-{:016x}", stable_hash(&key)));
+    let pch_path = pch_dir.join(format!("{PCH_HEADER}.gch"));
+    if pch_path.exists() {
+        return Some(pch_dir);
+    }
+
+    let mut header = Command::new("g++")
+        .args(&args)
+        .args(["-x", "c++", "-M", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut stdin = header.stdin.take()?;
+    writeln!(stdin, "#include <{PCH_HEADER}>").ok()?;
+    drop(stdin);
+    let deps = header.wait_with_output().ok()?.stdout;
+    let header = String::from_utf8_lossy(&deps)
+        .split_whitespace()
+        .find(|x| x.ends_with(PCH_HEADER))?
+        .to_owned();
+
+    // Written aside first, so that a half-built header is never picked up
+    let temp_path = pch_dir.join(format!("{}.gch", random_name()));
+    fs::create_dir_all(pch_path.parent()?).ok()?;
+    let built = run_step(
+        limits,
+        Command::new("g++")
+            .args(&args)
+            .args(["-x", "c++-header"])
+            .arg(header)
+            .arg("-o")
+            .arg(&temp_path),
+    )
+    .and_then(|_| fs::rename(&temp_path, &pch_path));
+
+    match built {
+        Ok(()) => Some(pch_dir),
+        Err(_) => {
+            fs::remove_file(&temp_path).ok();
+            None
+        }
+    }
+}
+
+/// Hashes the strings with 64-bit FNV-1a, which unlike `DefaultHasher` gives the same hash across
+/// Rust releases, so that it can name files kept between runs.
+fn stable_hash(parts: &[&str]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        // Each part ends with a zero byte, so that ["ab", "c"] and ["a", "bc"] differ
+        for &b in part.as_bytes().iter().chain(&[0]) {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+/// Compiles a C# source with Mono if it's installed, or with the .NET SDK otherwise.
+/// Mono builds an assembly run with `mono`, while .NET builds an executable run by itself.
+fn build_csharp(
+    code_path: &Path,
+    exec_path: &Path,
+    args: &[String],
+    limits: &CompileLimits,
+) -> Result<()> {
+    let mono = run_step(
+        limits,
+        Command::new("mcs")
+            .arg("-optimize+")
+            .args(args)
+            .arg(format!("-out:{}", exec_path.display()))
+            .arg(code_path),
+    );
+    match mono {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        result => return result,
+    }
+
+    let version = Command::new("dotnet").arg("--version").output()?;
+    let version = String::from_utf8_lossy(&version.stdout);
+    let framework = match version.trim().split('.').collect::<Vec<_>>()[..] {
+        [major, minor, ..] => format!("net{major}.{minor}"),
+        _ => return Err(io::Error::other("Couldn't read the version of dotnet")),
+    };
+
+    with_temp_dir("csharp", |project_dir| {
+        fs::write(
+            project_dir.join("main.csproj"),
+            format!(
+                "<Project Sdk=\"Microsoft.NET.Sdk\">\n  <PropertyGroup>\n    <OutputType>Exe</OutputType>\n    <TargetFramework>{framework}</TargetFramework>\n    <AssemblyName>main</AssemblyName>\n    <Nullable>disable</Nullable>\n    <AllowUnsafeBlocks>true</AllowUnsafeBlocks>\n  </PropertyGroup>\n</Project>\n"
+            ),
+        )?;
+        fs::copy(code_path, project_dir.join("Main.cs"))?;
+        run_step(
+            limits,
+            Command::new("dotnet")
+                .args(["publish", "-c", "Release", "--use-current-runtime"])
+                .args(["--self-contained", "false", "-p:PublishSingleFile=true"])
+                .args(args)
+                .arg("-o")
+                .arg(project_dir.join("out"))
+                .arg(project_dir),
+        )?;
+        let mut built = project_dir.join("out").join("main");
+        built.set_extension(env::consts::EXE_EXTENSION);
+        fs::copy(built, exec_path).map(drop)
+    })
+}
+
+/// Runs `f` in a fresh private directory, which is removed afterwards whether `f` fails or not.
+fn with_temp_dir<T>(prefix: &str, f: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+    let dir = env::temp_dir().join(format!("comparer-{prefix}-{}", random_name()));
+    fs::create_dir_all(&dir)?;
+    let result = f(&dir);
+    fs::remove_dir_all(&dir)?;
+    result
+}
+
+/// Returns whether the file is a .NET assembly built by Mono, which starts with a PE header.
+pub fn is_mono_assembly(path: impl AsRef<Path>) -> bool {
+    let mut header = [0u8; 2];
+    fs::File::open(path)
+        .and_then(|mut x| x.read_exact(&mut header))
+        .is_ok_and(|_| &header == b"MZ" && !cfg!(windows))
+}
+
+/// Pushes every file under `dir` with the given extension into `files`.
+fn collect_files(dir: &Path, extension: &str, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, extension, files)?;
+        } else if path.extension().is_some_and(|x| x == extension) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Finds the name of the top-level public type declared in a Java source.
+fn find_public_class(source: &str) -> Option<String> {
+    let source = strip_java_comments_and_literals(source);
+    let mut depth = 0usize;
+    let mut is_public = false;
+    let mut words = source
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$' || c == '{' || c == '}'))
+        .flat_map(|x| x.split_inclusive(['{', '}']))
+        .filter(|x| !x.is_empty());
+
+    while let Some(word) = words.next() {
+        match word.trim_end_matches(['{', '}']) {
+            "public" if depth == 0 => is_public = true,
+            "class" | "interface" | "enum" | "record" if depth == 0 && is_public => {
+                return words
+                    .next()
+                    .map(|x| x.trim_end_matches(['{', '}']).to_owned());
+            }
+            _ => {}
+        }
+        depth += word.matches('{').count();
+        depth = depth.saturating_sub(word.matches('}').count());
+        if word.ends_with(['{', '}']) {
+            is_public = false;
+        }
+    }
+    None
+}
+
+/// Replaces the comments and the string and character literals of a Java source with spaces, so
+/// that the words and braces in them aren't taken as code.
+fn strip_java_comments_and_literals(source: &str) -> String {
+    let mut stripped = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let (end, skip) = if rest.starts_with("//") {
+            ("\n", 2)
+        } else if rest.starts_with("/*") {
+            ("*/", 2)
+        } else if rest.starts_with("\"\"\"") {
+            ("\"\"\"", 3)
+        } else if c == '"' || c == '\'' {
+            (if c == '"' { "\"" } else { "'" }, 1)
+        } else {
+            stripped.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+
+        // Finds the end of the comment or the literal, skipping escaped characters in literals
+        let mut at = skip;
+        while at < rest.len() && !rest[at..].starts_with(end) {
+            let step = rest[at..].chars().next().map_or(1, char::len_utf8);
+            at += if rest[at..].starts_with('\\') && !rest.starts_with('/') {
+                1 + rest[at + 1..].chars().next().map_or(0, char::len_utf8)
+            } else {
+                step
+            };
+        }
+        let at = (at + end.len()).min(rest.len());
+        stripped.push(' ');
+        rest = &rest[at..];
+    }
+    stripped
+}
+
+/// Finds the binary name of the class declaring `main` from the output of `javap`.
+/// Top-level classes are preferred over nested ones.
+fn find_main_class(javap: &str) -> Option<String> {
+    let mut current: Option<&str> = None;
+    let mut found: Vec<&str> = Vec::new();
+
+    for line in javap.lines() {
+        if !line.starts_with(char::is_whitespace) && line.trim_end().ends_with('{') {
+            let mut words = line.split_whitespace();
+            current = words
+                .find(|&x| matches!(x, "class" | "interface" | "enum"))
+                .and_then(|_| words.next())
+                .map(|x| x.split('<').next().unwrap());
+        } else if line.contains("static void main(java.lang.String[])")
+            || line.contains("static void main(java.lang.String...)")
+        {
+            found.extend(current);
+        }
+    }
+
+    found.sort_by_key(|x| x.contains('$'));
+    found.first().map(|&x| x.to_owned())
+}
+
 pub fn absolute_path(path: impl AsRef<Path>) -> io::Result<PathBuf> {
     let path = path.as_ref();
 
@@ -147,3 +1066,101 @@ pub fn absolute_path(path: impl AsRef<Path>) -> io::Result<PathBuf> {
 
     Ok(absolute_path)
 }
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use std::{io, process::Command, time::Duration};
+
+    use super::{
+        cargo_bin_name, crate_name, find_main_class, find_public_class, is_project, run_step,
+        stable_hash, CompileLimits, RunLang, PROJECT_MARKER,
+    };
+    use crate::core::run_code::random_name;
+
+    #[cfg(unix)]
+    #[test]
+    fn compile_time_limit() {
+        let limits = CompileLimits {
+            time: Some(Duration::from_millis(100)),
+            memory: None,
+        };
+        let e = run_step(&limits, Command::new("sleep").arg("5")).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        run_step(&limits, &mut Command::new("true")).unwrap();
+    }
+
+    #[test]
+    fn pch_key_hash() {
+        // Known FNV-1a values, which must never change between builds
+        assert_eq!(stable_hash(&[]), 0xcbf29ce484222325);
+        assert_eq!(stable_hash(&["a"]), 0x089be207b544f1e4);
+        assert_ne!(stable_hash(&["ab", "c"]), stable_hash(&["a", "bc"]));
+    }
+
+    #[test]
+    fn detect_project() {
+        let dir = env::temp_dir().join(random_name());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("main.cpp"), "").unwrap();
+        fs::write(dir.join("brute.cpp"), "").unwrap();
+        assert!(!is_project(RunLang::Cpp, &dir));
+        assert!(!is_project(RunLang::Rust, &dir));
+
+        fs::write(dir.join(PROJECT_MARKER), "").unwrap();
+        fs::write(dir.join("Cargo.toml"), "").unwrap();
+        assert!(is_project(RunLang::Cpp, &dir));
+        assert!(is_project(RunLang::Rust, &dir));
+        assert!(!is_project(RunLang::Python, &dir));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn find_java_public_class() {
+        let source = "package a.b;\nimport java.util.*;\npublic class Solution<T> {\n    public static class Inner {}\n}\n";
+        assert_eq!(find_public_class(source).as_deref(), Some("Solution"));
+        assert_eq!(find_public_class("class Main {}"), None);
+
+        let commented = "// public class Fake {\n/* public class Fake2 { */\npublic class Sol {\n    String s = \"} public class Fake3 {\";\n    char c = '}';\n}\n";
+        assert_eq!(find_public_class(commented).as_deref(), Some("Sol"));
+        let text_block = "class A {\n    String s = \"\"\"\n        \" }\n        \"\"\";\n}\npublic class B {}\n";
+        assert_eq!(find_public_class(text_block).as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn rlib_crate_name() {
+        assert_eq!(
+            crate_name("libproconio-5d1b6b8a1f0c2e3d").as_deref(),
+            Some("proconio")
+        );
+        assert_eq!(crate_name("libnum_integer").as_deref(), Some("num_integer"));
+        assert_eq!(crate_name("main"), None);
+    }
+
+    #[test]
+    fn cargo_manifest_bin_name() {
+        let package = "[package]\nname = \"sol\"\nversion = \"0.1.0\"\n";
+        assert_eq!(cargo_bin_name(package).unwrap(), "sol");
+        let bins = format!("{package}\n[[bin]]\nname = \"main\"\npath = \"src/main.rs\"\n");
+        assert_eq!(cargo_bin_name(&bins).unwrap(), "main");
+    }
+
+    #[test]
+    fn find_java_main_class() {
+        let javap = r#"Compiled from "Main.java"
+class com.example.Main$Solver {
+  com.example.Main$Solver();
+  public static void main(java.lang.String[]);
+}
+Compiled from "Main.java"
+public final class com.example.Main extends java.lang.Object implements java.lang.Runnable {
+  public com.example.Main();
+  public void run();
+  public static void main(java.lang.String[]) throws java.io.IOException;
+}
+"#;
+        assert_eq!(find_main_class(javap).as_deref(), Some("com.example.Main"));
+        assert_eq!(find_main_class("public class A {\n}\n"), None);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Result},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::core::compile::{CompileLimits, CompileOptions, LangFlags, RunLang};
+
+/// The default location of the project config, relative to the working directory.
+pub const CONFIG_PATH_DEFAULT: &str = "./comparer.toml";
+
+/// The default directory of build artifacts shared between compilations.
+pub const CACHE_DIR_DEFAULT: &str = "./compile/cache/";
+
+/// The default time limit of a single step of compilation, in milliseconds.
+pub const COMPILE_TIME_LIMIT_DEFAULT: u64 = 60000;
+
+/// Project-wide settings read from a TOML file.
+///
+/// ```toml
+/// byte_compile = true
+/// compile_time_limit = 30000 # ms
+/// compile_memory_limit = 2048 # MiB
+///
+/// [lang.cpp]
+/// std = "gnu++20"
+/// extra = ["-DLOCAL", "-march=native"]
+///
+/// [lang.rust]
+/// std = "2018"
+///
+/// [judges.atcoder]
+/// rust_deps = "./judges/atcoder"
+/// ```
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Byte-compiles Python codes before testing, to catch syntax errors early.
+    pub byte_compile: bool,
+    /// Time limit of each step of compilation in milliseconds, defaulting at 60000.
+    pub compile_time_limit: Option<u64>,
+    /// Memory limit of each step of compilation in MiB. Unlimited if not given.
+    pub compile_memory_limit: Option<u64>,
+    /// Directory of build artifacts shared between compilations, defaulting at ./compile/cache/.
+    pub cache_dir: Option<PathBuf>,
+    /// The judge environment used if none is given from the command line.
+    pub judge: Option<String>,
+    /// Compiler flags keyed by language name, e.g. `cpp` or `rust`.
+    pub lang: HashMap<String, LangFlags>,
+    /// Named judge environments, e.g. `atcoder`.
+    pub judges: HashMap<String, JudgeEnv>,
+}
+
+/// The compilation environment of an online judge.
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JudgeEnv {
+    /// Crates Rust codes are compiled against: a directory of prebuilt rlibs, or a vendored
+    /// Cargo workspace. Relative paths start from the working directory.
+    pub rust_deps: Option<PathBuf>,
+    /// Compiler flags of the judge, replacing the ones of the project for each language given.
+    pub lang: HashMap<String, LangFlags>,
+}
+
+impl Config {
+    /// Reads the config at `path`. A missing file is treated as an empty config.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content)
+    }
+
+    /// Parses a config from its TOML representation.
+    pub fn parse(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Returns the compile options described by the config, in the given judge environment or
+    /// the default one of the config.
+    pub fn compile_options(&self, judge: Option<&str>) -> Result<CompileOptions> {
+        let mut options = CompileOptions {
+            byte_compile: self.byte_compile,
+            limits: CompileLimits {
+                time: Some(Duration::from_millis(
+                    self.compile_time_limit
+                        .unwrap_or(COMPILE_TIME_LIMIT_DEFAULT),
+                )),
+                memory: self
+                    .compile_memory_limit
+                    .map(memory_limit_bytes)
+                    .transpose()?,
+            },
+            cache_dir: Some(
+                self.cache_dir
+                    .clone()
+                    .unwrap_or_else(|| CACHE_DIR_DEFAULT.into()),
+            ),
+            ..Default::default()
+        };
+        for (name, flags) in self.lang.iter() {
+            let lang: RunLang = name.as_str().try_into()?;
+            options.flags.insert(lang, flags.clone());
+        }
+
+        if let Some(judge) = judge.or(self.judge.as_deref()) {
+            let env = self.judges.get(judge).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown judge environment: {judge}"),
+                )
+            })?;
+            for (name, flags) in env.lang.iter() {
+                let lang: RunLang = name.as_str().try_into()?;
+                options.flags.insert(lang, flags.clone());
+            }
+            options.rust_deps = env.rust_deps.clone();
+        }
+        Ok(options)
+    }
+}
+
+/// Converts a memory limit of compilation in MiB to bytes, failing if it doesn't fit in 64 bits.
+pub fn memory_limit_bytes(mib: u64) -> Result<u64> {
+    mib.checked_mul(1 << 20).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("The compile memory limit of {mib} MiB is too large"),
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{memory_limit_bytes, Config};
+    use crate::core::compile::RunLang;
+
+    #[test]
+    fn parse_lang_flags() {
+        let config = Config::parse(
+            r#"
+            [lang.cpp]
+            std = "gnu++20"
+            extra = ["-DLOCAL"]
+
+            [lang.rs]
+            flags = []
+            std = "2018"
+            "#,
+        )
+        .unwrap();
+        let options = config.compile_options(None).unwrap();
+
+        assert_eq!(
+            options.args(RunLang::Cpp),
+            ["-O2", "-Wall", "-lm", "-static", "-std=gnu++20", "-DLOCAL"]
+        );
+        assert_eq!(options.args(RunLang::Rust), ["--edition", "2018"]);
+        assert_eq!(options.args(RunLang::C).last().unwrap(), "-std=gnu11");
+    }
+
+    #[test]
+    fn select_judge_env() {
+        let config = Config::parse(
+            r#"
+            judge = "local"
+
+            [judges.local]
+
+            [judges.atcoder]
+            rust_deps = "./judges/atcoder"
+
+            [judges.atcoder.lang.cpp]
+            std = "gnu++20"
+            "#,
+        )
+        .unwrap();
+
+        let local = config.compile_options(None).unwrap();
+        assert!(local.rust_deps.is_none());
+        let atcoder = config.compile_options(Some("atcoder")).unwrap();
+        assert!(atcoder.rust_deps.is_some());
+        assert_eq!(atcoder.args(RunLang::Cpp).last().unwrap(), "-std=gnu++20");
+        assert!(config.compile_options(Some("codeforces")).is_err());
+    }
+
+    #[test]
+    fn compile_memory_limit() {
+        assert_eq!(memory_limit_bytes(2048).unwrap(), 2048 << 20);
+        let config = Config::parse("compile_memory_limit = 17592186044416").unwrap();
+        assert!(config.compile_options(None).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt::Write;
+
+use console::style;
+
+use crate::core::string::truncate_line;
+
+/// The number of lines shown before and after the first differing line.
+pub const CONTEXT_LINES: usize = 3;
+
+/// The number of characters of a line shown in a diff.
+pub const LINE_WIDTH: usize = 200;
+
+/// Returns the lines of an output as `process_str` would compare them.
+fn output_lines(s: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = s.lines().map(str::trim_end).collect();
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Renders how `actual` differs from `expected`, line by line. The first differing line is shown
+/// with `context` lines around it, along with its first differing token and the number of lines
+/// which differ in total. Lines of `expected` are marked with `-`, and the ones of `actual` with
+/// `+`, colored if the terminal supports it.
+pub fn render_diff(expected: &str, actual: &str, context: usize) -> String {
+    let expected = output_lines(expected);
+    let actual = output_lines(actual);
+    let total = expected.len().max(actual.len());
+    let differs = |i: usize| expected.get(i) != actual.get(i);
+
+    let Some(first) = (0..total).find(|&i| differs(i)) else {
+        return "The outputs only differ in trailing whitespace\n".to_owned();
+    };
+    let differing = (0..total).filter(|&i| differs(i)).count();
+
+    let mut builder = String::new();
+    writeln!(
+        builder,
+        "{}",
+        first_difference(first + 1, expected.get(first), actual.get(first))
+    )
+    .unwrap();
+
+    let start = first.saturating_sub(context);
+    let end = (first + context + 1).min(total);
+    let width = end.to_string().len();
+    if start > 0 {
+        writeln!(builder, "  ...").unwrap();
+    }
+    for i in start..end {
+        let number = i + 1;
+        match (expected.get(i), actual.get(i)) {
+            (Some(x), Some(y)) if x == y => {
+                let line = format!("  {number:>width$} | {}", truncate_line(x, LINE_WIDTH));
+                writeln!(builder, "{}", style(line).dim()).unwrap();
+            }
+            (x, y) => {
+                if let Some(x) = x {
+                    let line = format!("- {number:>width$} | {}", truncate_line(x, LINE_WIDTH));
+                    writeln!(builder, "{}", style(line).red()).unwrap();
+                }
+                if let Some(y) = y {
+                    let line = format!("+ {number:>width$} | {}", truncate_line(y, LINE_WIDTH));
+                    writeln!(builder, "{}", style(line).green()).unwrap();
+                }
+            }
+        }
+    }
+    if end < total {
+        writeln!(builder, "  ...").unwrap();
+    }
+
+    writeln!(builder, "{differing} of {total} lines differ").unwrap();
+    builder
+}
+
+/// Describes the first differing token of the first differing line.
+fn first_difference(number: usize, expected: Option<&&str>, actual: Option<&&str>) -> String {
+    let (x, y) = match (expected, actual) {
+        (Some(x), Some(y)) => (x, y),
+        (Some(_), None) => return format!("Line {number}: the output ends early"),
+        (None, _) => return format!("Line {number}: extra output"),
+    };
+
+    let mut expected_tokens = x.split_whitespace();
+    let mut actual_tokens = y.split_whitespace();
+    for token in 1.. {
+        match (expected_tokens.next(), actual_tokens.next()) {
+            (Some(a), Some(b)) if a == b => continue,
+            (Some(a), Some(b)) => {
+                return format!(
+                    "Line {number}, token {token}: expected {}, found {}",
+                    truncate_line(a, LINE_WIDTH),
+                    truncate_line(b, LINE_WIDTH)
+                );
+            }
+            (Some(a), None) => {
+                return format!(
+                    "Line {number}, token {token}: expected {}, found the end of the line",
+                    truncate_line(a, LINE_WIDTH)
+                );
+            }
+            (None, Some(b)) => {
+                return format!(
+                    "Line {number}, token {token}: extra token {}",
+                    truncate_line(b, LINE_WIDTH)
+                );
+            }
+            (None, None) => break,
+        }
+    }
+    format!("Line {number}: only whitespace differs")
+}
+
+#[cfg(test)]
+mod test {
+    use super::render_diff;
+
+    #[test]
+    fn first_differing_line() {
+        console::set_colors_enabled(false);
+        let expected = "1\n2\n3\n4 5 6\n7\n8\n9\n10\n";
+        let actual = "1\n2\n3\n4 5 0\n7\n8\n9\n11\n";
+        let lines = [
+            "Line 4, token 3: expected 6, found 0",
+            "  ...",
+            "  3 | 3",
+            "- 4 | 4 5 6",
+            "+ 4 | 4 5 0",
+            "  5 | 7",
+            "  ...",
+            "2 of 8 lines differ",
+        ];
+        assert_eq!(render_diff(expected, actual, 1), lines.join("\n") + "\n");
+        assert!(render_diff("1\n", "1  \n\n", 3).contains("trailing whitespace"));
+        assert!(render_diff("1\n2\n", "1\n", 3).starts_with("Line 2: the output ends early"));
+    }
+}
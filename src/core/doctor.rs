@@ -0,0 +1,264 @@
+use std::{fs, path::PathBuf, process::Command, time::Duration};
+
+use tokio::io::Result;
+
+use crate::core::{
+    compile::{compile_with, CompileOptions, RunLang},
+    run_code::get_results,
+};
+
+const SAMPLE_INPUT: &str = "1 2";
+const SAMPLE_OUTPUT: &str = "3";
+const SAMPLE_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// The outcome of building and running a sample program of a language.
+#[derive(Debug)]
+pub enum BuildCheck {
+    /// The sample program was built with the configured flags and gave the right answer.
+    Ok,
+    /// The sample program only builds after removing `-static` from the flags, which are given.
+    /// `compare` links with `-static` by default, so the language needs these flags to be used.
+    DynamicOnly(Vec<String>),
+    /// The sample program couldn't be built or gave a wrong answer.
+    Failed(String),
+    /// Some of the tools are missing, so nothing was built.
+    Skipped,
+}
+
+/// The diagnosis of the toolchain of a single language.
+#[derive(Debug)]
+pub struct LangReport {
+    pub lang: RunLang,
+    /// Every program the language needs, with the first line of its version if it's installed.
+    pub tools: Vec<(&'static str, Option<String>)>,
+    pub build: BuildCheck,
+}
+
+impl LangReport {
+    /// Returns whether `compare` can be used with the language on this machine with the configured
+    /// flags.
+    pub fn is_usable(&self) -> bool {
+        matches!(self.build, BuildCheck::Ok)
+    }
+}
+
+/// Returns the programs used to compile and run `lang`, with the arguments printing their versions.
+pub fn toolchain(lang: RunLang) -> &'static [(&'static str, &'static [&'static str])] {
+    use RunLang::*;
+    match lang {
+        C => &[("gcc", &["--version"])],
+        Cpp => &[("g++", &["--version"])],
+        Python => &[("python3", &["--version"])],
+        PyPy => &[("pypy3", &["--version"])],
+        Java => &[
+            ("javac", &["-version"]),
+            ("javap", &["-version"]),
+            ("jar", &["--version"]),
+            ("java", &["-version"]),
+        ],
+        Go => &[("go", &["version"])],
+        Rust => &[("rustc", &["--version"])],
+        Kotlin => &[("kotlinc", &["-version"]), ("java", &["-version"])],
+        Scala => &[("scalac", &["-version"]), ("scala", &["-version"])],
+        // Mono is preferred by `compile` if it's installed
+        CSharp if probe_version("mcs", &["--version"]).is_some() => {
+            &[("mcs", &["--version"]), ("mono", &["--version"])]
+        }
+        CSharp => &[("dotnet", &["--version"])],
+        JavaScript => &[("node", &["--version"])],
+        Ruby => &[("ruby", &["--version"])],
+        Haskell => &[("ghc", &["--version"])],
+        OCaml => &[("ocamlopt", &["-version"])],
+        Exec => &[],
+    }
+}
+
+/// Returns the file name and the code of a program printing the sum of two integers in `lang`,
+/// or None for exec, which has no code.
+pub fn sample_source(lang: RunLang) -> Option<(&'static str, &'static str)> {
+    use RunLang::*;
+    Some(match lang {
+        C => (
+            "main.c",
+            "#include <stdio.h>\nint main(void) {\n    int a, b;\n    scanf(\"%d %d\", &a, &b);\n    printf(\"%d\\n\", a + b);\n    return 0;\n}\n",
+        ),
+        Cpp => (
+            "main.cpp",
+            "#include <iostream>\nint main() {\n    int a, b;\n    std::cin >> a >> b;\n    std::cout << a + b << '\\n';\n}\n",
+        ),
+        Python | PyPy => ("main.py", "a, b = map(int, input().split())\nprint(a + b)\n"),
+        Java => (
+            "Main.java",
+            "import java.util.Scanner;\npublic class Main {\n    public static void main(String[] args) {\n        Scanner sc = new Scanner(System.in);\n        System.out.println(sc.nextInt() + sc.nextInt());\n    }\n}\n",
+        ),
+        Go => (
+            "main.go",
+            "package main\n\nimport \"fmt\"\n\nfunc main() {\n\tvar a, b int\n\tfmt.Scan(&a, &b)\n\tfmt.Println(a + b)\n}\n",
+        ),
+        Kotlin => (
+            "main.kt",
+            "fun main() {\n    val (a, b) = readLine()!!.split(\" \").map { it.toInt() }\n    println(a + b)\n}\n",
+        ),
+        Scala => (
+            "main.scala",
+            "object Main {\n  def main(args: Array[String]): Unit = {\n    val Array(a, b) = scala.io.StdIn.readLine().split(\" \").map(_.toInt)\n    println(a + b)\n  }\n}\n",
+        ),
+        CSharp => (
+            "main.cs",
+            "using System;\n\nclass Program {\n    static void Main() {\n        var v = Console.ReadLine().Split(' ');\n        Console.WriteLine(int.Parse(v[0]) + int.Parse(v[1]));\n    }\n}\n",
+        ),
+        JavaScript => (
+            "main.js",
+            "const [a, b] = require(\"fs\").readFileSync(0, \"utf8\").trim().split(/\\s+/).map(Number);\nconsole.log(a + b);\n",
+        ),
+        Ruby => ("main.rb", "a, b = gets.split.map(&:to_i)\nputs a + b\n"),
+        Haskell => (
+            "main.hs",
+            "main :: IO ()\nmain = do\n    [a, b] <- map read . words <$> getLine\n    print (a + b :: Int)\n",
+        ),
+        OCaml => (
+            "main.ml",
+            "let () = Scanf.scanf \" %d %d\" (fun a b -> Printf.printf \"%d\\n\" (a + b))\n",
+        ),
+        Exec => return None,
+        Rust => (
+            "main.rs",
+            "fn main() {\n    let mut s = String::new();\n    std::io::stdin().read_line(&mut s).unwrap();\n    let v: Vec<i32> = s.split_whitespace().map(|x| x.parse().unwrap()).collect();\n    println!(\"{}\", v[0] + v[1]);\n}\n",
+        ),
+    })
+}
+
+/// Returns the first line a program prints with the given arguments, or None if it can't be run.
+pub fn probe_version(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    // Some programs, e.g. java, print their version to the standard error
+    [output.stdout, output.stderr]
+        .iter()
+        .flat_map(|x| {
+            String::from_utf8_lossy(x)
+                .lines()
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        })
+        .find(|l| !l.trim().is_empty())
+        .or_else(|| Some(String::new()))
+}
+
+/// Checks whether `lang` can be compiled and run with the given options.
+pub async fn diagnose(lang: RunLang, options: &CompileOptions) -> Result<LangReport> {
+    let tools: Vec<_> = toolchain(lang)
+        .iter()
+        .map(|&(program, args)| (program, probe_version(program, args)))
+        .collect();
+
+    let build = if tools.iter().any(|(_, version)| version.is_none()) {
+        BuildCheck::Skipped
+    } else {
+        match build_sample(lang, options).await? {
+            Ok(()) => BuildCheck::Ok,
+            Err(e) if options.args(lang).iter().any(|x| x == "-static") => {
+                let mut dynamic = options.clone();
+                let base: Vec<_> = options
+                    .base_args(lang)
+                    .into_iter()
+                    .filter(|x| x != "-static")
+                    .collect();
+                dynamic.flags.entry(lang).or_default().flags = Some(base.clone());
+                match build_sample(lang, &dynamic).await? {
+                    Ok(()) => BuildCheck::DynamicOnly(base),
+                    Err(_) => BuildCheck::Failed(e),
+                }
+            }
+            Err(e) => BuildCheck::Failed(e),
+        }
+    };
+
+    Ok(LangReport { lang, tools, build })
+}
+
+/// Builds and runs the sample program, returning the reason of the failure if there is one.
+async fn build_sample(
+    lang: RunLang,
+    options: &CompileOptions,
+) -> Result<std::result::Result<(), String>> {
+    let Some((file_name, code)) = sample_source(lang) else {
+        return Ok(Err(format!("{} has no sample program", lang.name())));
+    };
+    let dir: PathBuf = format!("./compile/temp/doctor-{}/", lang.name()).into();
+    fs::create_dir_all(&dir)?;
+
+    fs::write(dir.join(file_name), code)?;
+
+    let result = match compile_with(lang, dir.join(file_name), &dir, "doctor", options) {
+        Ok(prog) => {
+            let inputs = [SAMPLE_INPUT.to_owned()];
+            let output = get_results(lang, prog, inputs, SAMPLE_TIME_LIMIT).await;
+            let output = String::from_utf8_lossy(&output[0]);
+            if output.trim() == SAMPLE_OUTPUT {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Wrong output from the sample program: {}",
+                    output.trim()
+                ))
+            }
+        }
+        Err(e) => Err(e.to_string()),
+    };
+
+    fs::remove_dir_all(&dir)?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diagnose, probe_version, toolchain, BuildCheck, LangReport};
+    use crate::core::compile::{CompileOptions, RunLang};
+
+    fn is_installed(lang: RunLang) -> bool {
+        toolchain(lang)
+            .iter()
+            .all(|(program, args)| probe_version(program, args).is_some())
+    }
+
+    #[test]
+    fn dynamic_only_is_not_usable() {
+        let report = |build| LangReport {
+            lang: RunLang::Cpp,
+            tools: vec![],
+            build,
+        };
+        assert!(report(BuildCheck::Ok).is_usable());
+        assert!(!report(BuildCheck::DynamicOnly(vec!["-O2".to_owned()])).is_usable());
+        assert!(!report(BuildCheck::Failed(String::new())).is_usable());
+        assert!(!report(BuildCheck::Skipped).is_usable());
+    }
+
+    #[tokio::test]
+    async fn diagnose_installed_languages() {
+        for lang in [RunLang::Python, RunLang::Cpp] {
+            if !is_installed(lang) {
+                eprintln!("Skipping {}: it's not installed", lang.name());
+                continue;
+            }
+            let report = diagnose(lang, &CompileOptions::default()).await.unwrap();
+            assert!(
+                matches!(report.build, BuildCheck::Ok | BuildCheck::DynamicOnly(_)),
+                "{:?}",
+                report.build
+            );
+            assert!(report.tools.iter().all(|(_, version)| version.is_some()));
+        }
+
+        if is_installed(RunLang::Cpp) {
+            let mut options = CompileOptions::default();
+            options.push_flag(RunLang::Cpp, "--no-such-flag");
+            let report = diagnose(RunLang::Cpp, &options).await.unwrap();
+            assert!(
+                matches!(report.build, BuildCheck::Failed(_)),
+                "{:?}",
+                report.build
+            );
+        }
+    }
+}
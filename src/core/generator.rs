@@ -0,0 +1,122 @@
+use std::{path::PathBuf, time::Duration};
+
+use tokio::io::{self, Result};
+
+use crate::core::{
+    compile::RunLang,
+    run_code::{invocation, run_output},
+    string::escape_bytes,
+};
+
+/// The time a generator may take to print a single testcase.
+pub const GENERATOR_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// A generator program in any language, following the convention of testlib generators. It's
+/// run as `gen <seed> [args...]`, and prints a testcase to its standard output.
+#[derive(Clone, Debug)]
+pub struct ExternalGenerator {
+    /// The language the generator was written in.
+    pub lang: RunLang,
+    /// The generator, as returned by `compile`.
+    pub prog: PathBuf,
+    /// Arguments given after the seed, e.g. the size of the testcase.
+    pub args: Vec<String>,
+}
+
+impl ExternalGenerator {
+    /// Runs the generator with the given seed, and returns the testcase it printed. It fails if
+    /// the generator exits with a failure, with its standard error in the message.
+    pub async fn generate(&self, seed: u64) -> Result<String> {
+        self.generate_within(seed, GENERATOR_TIME_LIMIT).await
+    }
+
+    async fn generate_within(&self, seed: u64, time_limit: Duration) -> Result<String> {
+        let (program, mut args) = invocation(self.lang, &self.prog);
+        #[cfg(unix)]
+        if self.lang == RunLang::Exec {
+            // Arguments of `sh -c` start from $0, so the seed is given as $1
+            args.push("gen".into());
+        }
+        args.push(seed.to_string().into());
+        args.extend(self.args.iter().map(Into::into));
+
+        let output = run_output(program, &args, "", "./compile/temp/", time_limit).await?;
+        let status = match output.status {
+            Some(status) => status,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "The generator ran longer than {}ms with seed {seed}",
+                        time_limit.as_millis()
+                    ),
+                ))
+            }
+        };
+        if !status.success() {
+            let stderr = match escape_bytes(&output.stderr).trim() {
+                "" => String::new(),
+                stderr => format!(": {stderr}"),
+            };
+            return Err(io::Error::other(format!(
+                "The generator exited with {status} with seed {seed}{stderr}"
+            )));
+        }
+        String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Runs the generator once for each seed at once.
+    pub async fn generate_multi(&self, seeds: Vec<u64>) -> Result<Vec<String>> {
+        let handles: Vec<_> = seeds
+            .into_iter()
+            .map(|seed| {
+                let generator = self.clone();
+                tokio::spawn(async move { generator.generate(seed).await })
+            })
+            .collect();
+
+        let mut inputs = Vec::with_capacity(handles.len());
+        for h in handles {
+            inputs.push(h.await??);
+        }
+        Ok(inputs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::ExternalGenerator;
+    use crate::core::run_code::{exec_fixture as exec, ExecFixture};
+
+    impl From<ExecFixture> for ExternalGenerator {
+        fn from(x: ExecFixture) -> Self {
+            Self {
+                lang: x.lang,
+                prog: x.prog,
+                args: Vec::new(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn generate() {
+        let args = ExternalGenerator {
+            args: vec!["7".to_owned()],
+            ..exec(r#"printf '%s %s %s' "$0" "$1" "$2""#)
+        };
+        assert_eq!(args.generate(5).await.unwrap(), "gen 5 7");
+
+        let warning: ExternalGenerator = exec("echo 5; echo warn >&2");
+        assert_eq!(warning.generate(0).await.unwrap(), "5\n");
+
+        let crash: ExternalGenerator = exec("echo 5; echo broken >&2; exit 3");
+        let e = crash.generate(1).await.unwrap_err().to_string();
+        assert!(e.contains("seed 1") && e.contains("broken"), "{e}");
+
+        let slow: ExternalGenerator = exec("sleep 5");
+        let e = slow.generate_within(2, Duration::from_millis(100)).await;
+        assert_eq!(e.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+    }
+}
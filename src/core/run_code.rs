@@ -1,22 +1,60 @@
 use core::time;
 use std::{
     borrow::Borrow,
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
     path::{Path, PathBuf},
-    process::Stdio,
-    time::Duration,
+    process::{ExitStatus, Stdio},
+    time::{Duration, Instant},
 };
 
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use tokio::{
     fs::{self, File},
-    io::{AsyncReadExt, AsyncWriteExt, Result},
+    io::{AsyncWriteExt, Result},
     process::Command,
     time::timeout,
 };
 
-use crate::core::compile::RunLang;
+use crate::core::compile::{is_mono_assembly, RunLang};
 
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[cfg(unix)]
+const SHELL: &str = "sh";
+#[cfg(unix)]
+const SHELL_COMMAND_FLAG: &str = "-c";
+#[cfg(windows)]
+const SHELL: &str = "cmd";
+#[cfg(windows)]
+const SHELL_COMMAND_FLAG: &str = "/C";
+
+/// The full result of a single execution of a program. The outputs are kept as they were
+/// printed, which may not be valid UTF-8.
+#[derive(Clone, Debug, Default)]
+pub struct RunOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub timed_out: bool,
+    /// The exit status, or None if the program timed out or couldn't be run.
+    pub status: Option<ExitStatus>,
+}
+
+impl RunOutput {
+    /// Returns the output as reported by `run`: "Timeout", the standard error if it's not empty,
+    /// or the standard output otherwise.
+    pub fn into_result(self) -> Vec<u8> {
+        if self.timed_out {
+            b"Timeout".to_vec()
+        } else if self.stderr.is_empty() {
+            self.stdout
+        } else {
+            self.stderr
+        }
+    }
+}
+
+/// Same as `run_output`, but returns the output as reported by `RunOutput::into_result`, with
+/// invalid UTF-8 replaced.
 pub async fn run(
     command: impl AsRef<OsStr>,
     args: &[impl AsRef<OsStr>],
@@ -24,39 +62,109 @@ pub async fn run(
     dir_input: impl AsRef<Path>,
     duration: time::Duration,
 ) -> Result<String> {
+    let output = run_output(command, args, input, dir_input, duration).await?;
+    Ok(String::from_utf8_lossy(&output.into_result()).into_owned())
+}
+
+/// Runs the command with the input redirected to its standard input, and returns both of its
+/// outputs. The process is killed if it doesn't finish in the given duration.
+pub async fn run_output(
+    command: impl AsRef<OsStr>,
+    args: &[impl AsRef<OsStr>],
+    input: &str,
+    dir_input: impl AsRef<Path>,
+    duration: time::Duration,
+) -> Result<RunOutput> {
     let input_loc = generate_file_with_random_name(&dir_input, input).await?;
     let input_file = fs::File::open(&input_loc).await?;
 
-    let mut proc = Command::new(command)
+    let mut command = std::process::Command::new(command);
+    command
         .args(args)
         .stdin(Stdio::from(input_file.into_std().await))
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+        .stderr(Stdio::piped());
+    own_process_group(&mut command);
+    let proc = Command::from(command).kill_on_drop(true).spawn()?;
+    let pid = proc.id();
 
-    if timeout(duration, proc.wait()).await.is_err() {
-        fs::remove_file(input_loc).await?;
-        return Ok("Timeout".to_owned());
-    }
+    // Both pipes are drained while waiting, so a large output can't block the child
+    let output = timeout(duration, proc.wait_with_output()).await;
+    fs::remove_file(input_loc).await?;
 
-    proc.wait().await?;
+    match output {
+        Err(_) => {
+            kill_process_group(pid);
+            Ok(RunOutput {
+                timed_out: true,
+                ..Default::default()
+            })
+        }
+        Ok(output) => {
+            let output = output?;
+            Ok(RunOutput {
+                stdout: output.stdout,
+                stderr: output.stderr,
+                timed_out: false,
+                status: Some(output.status),
+            })
+        }
+    }
+}
 
-    let mut out = String::new();
-    proc.stdout.unwrap().read_to_string(&mut out).await?;
+/// Waits for a child spawned outside of tokio, and kills it if it doesn't finish in the duration,
+/// like `run_output` does. Returns None if the child was killed.
+///
+/// The child should be spawned with `own_process_group`, so that the processes it spawned (e.g.
+/// `cc1plus` under `g++`) are killed along with it.
+pub fn wait_or_kill(
+    child: &mut std::process::Child,
+    duration: Option<Duration>,
+) -> Result<Option<ExitStatus>> {
+    let Some(duration) = duration else {
+        return child.wait().map(Some);
+    };
 
-    let mut err = String::new();
-    proc.stderr.unwrap().read_to_string(&mut err).await?;
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if start.elapsed() >= duration {
+            kill_process_group(Some(child.id()));
+            #[cfg(not(unix))]
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        std::thread::sleep(WAIT_POLL_INTERVAL.min(duration.saturating_sub(start.elapsed())));
+    }
+}
 
-    fs::remove_file(input_loc).await?;
+/// Makes the command lead its own process group, so that `kill_process_group` also kills the
+/// processes it spawns, e.g. those of a shell pipeline run by `RunLang::Exec`.
+pub(crate) fn own_process_group(command: &mut std::process::Command) -> &mut std::process::Command {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+    command
+}
 
-    if err.is_empty() {
-        Ok(out)
-    } else {
-        Ok(err)
+/// Kills the process group led by the process of the given id, spawned with `own_process_group`.
+/// Elsewhere than on unix, nothing is done, so the process itself has to be killed.
+pub(crate) fn kill_process_group(pid: Option<u32>) {
+    #[cfg(unix)]
+    if let Some(pid) = pid {
+        // SAFETY: A plain system call with no memory passed
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
     }
 }
 
-async fn generate_file_with_random_name(dir: impl AsRef<Path>, content: &str) -> Result<PathBuf> {
+pub(crate) async fn generate_file_with_random_name(
+    dir: impl AsRef<Path>,
+    content: &str,
+) -> Result<PathBuf> {
     let mut file_path = dir.as_ref().to_owned();
     file_path.push(random_name());
     file_path.set_extension("txt");
@@ -70,61 +178,96 @@ pub async fn get_results(
     prog: impl AsRef<Path>,
     inputs: impl Borrow<[String]>,
     time_limit: Duration,
-) -> Vec<String> {
+) -> Vec<Vec<u8>> {
+    get_outputs(lang, prog, inputs, time_limit)
+        .await
+        .into_iter()
+        .map(RunOutput::into_result)
+        .collect()
+}
+
+/// Same as `get_results`, but keeps the standard output and the standard error apart.
+/// For `RunLang::Exec`, `prog` is the command line to run through the shell.
+///
+/// A program which fails to run is given the reason as its standard error.
+pub async fn get_outputs(
+    lang: RunLang,
+    prog: impl AsRef<Path>,
+    inputs: impl Borrow<[String]>,
+    time_limit: Duration,
+) -> Vec<RunOutput> {
     let inputs = inputs.borrow();
     let prog = prog.as_ref();
-    let mut cr_handles: Vec<_> = Vec::with_capacity(inputs.len());
-
-    for input in inputs.iter().cloned() {
-        let h = match lang {
-            RunLang::Python => {
-                let arr: Vec<_> = vec![prog.to_owned()];
-                tokio::spawn(async move {
-                    run("python3", &arr, &input, "./compile/temp/", time_limit).await
-                })
-            }
-
-            RunLang::Java => {
-                let arr: Vec<_> = vec![
-                    "-classpath".to_owned(),
-                    prog.to_owned().to_str().unwrap().to_owned(),
-                    "Main".to_owned(),
-                ];
-                tokio::spawn(async move {
-                    run("java", &arr, &input, "./compile/temp/", time_limit).await
-                })
-            }
-
-            _ => {
-                let prog = prog.to_owned();
-                tokio::spawn(async move {
-                    run(
-                        prog,
-                        &[] as &[String],
-                        &input,
-                        "./compile/temp/",
-                        time_limit,
-                    )
-                    .await
-                })
-            }
-        };
+    let (program, args) = invocation(lang, prog);
+    let cr_handles: Vec<_> = inputs
+        .iter()
+        .map(|input| {
+            let (program, args, input) = (program.clone(), args.clone(), input.clone());
+            tokio::spawn(async move {
+                run_output(program, &args, &input, "./compile/temp/", time_limit).await
+            })
+        })
+        .collect();
 
-        cr_handles.push(h);
-    }
-
-    let mut arr: Vec<String> = Vec::with_capacity(cr_handles.len());
+    let mut arr: Vec<RunOutput> = Vec::with_capacity(cr_handles.len());
     for h in cr_handles {
-        let x = h.await.unwrap().unwrap();
+        let x = match h.await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => failed_output(e),
+            Err(e) => failed_output(e),
+        };
         arr.push(x);
     }
     arr
 }
 
-fn random_name() -> String {
+fn failed_output(error: impl std::fmt::Display) -> RunOutput {
+    RunOutput {
+        stderr: format!("Failed to run the program: {error}").into_bytes(),
+        ..Default::default()
+    }
+}
+
+/// Returns the program and the arguments which run `prog`, built by `compile` from a code in
+/// `lang`. For `RunLang::Exec`, `prog` is the command line to run through the shell.
+pub fn invocation(lang: RunLang, prog: &Path) -> (OsString, Vec<OsString>) {
+    let prog = prog.as_os_str().to_owned();
+    match lang {
+        RunLang::Python => ("python3".into(), vec![prog]),
+        RunLang::PyPy => ("pypy3".into(), vec![prog]),
+        // The main class is recorded in the manifest of the jar by `compile`
+        RunLang::Java | RunLang::Kotlin => ("java".into(), vec!["-jar".into(), prog]),
+        RunLang::JavaScript => ("node".into(), vec![prog]),
+        RunLang::Ruby => ("ruby".into(), vec![prog]),
+        RunLang::Scala => ("scala".into(), vec![prog]),
+        RunLang::CSharp if is_mono_assembly(&prog) => ("mono".into(), vec![prog]),
+        RunLang::Exec => (SHELL.into(), vec![SHELL_COMMAND_FLAG.into(), prog]),
+        _ => (prog, vec![]),
+    }
+}
+
+pub(crate) fn random_name() -> String {
     thread_rng()
         .sample_iter(&Alphanumeric)
         .take(30)
         .map(char::from)
         .collect()
 }
+
+/// A shell snippet run as a program with `RunLang::Exec`, with the time limit the tests give it.
+#[cfg(test)]
+pub(crate) struct ExecFixture {
+    pub lang: RunLang,
+    pub prog: PathBuf,
+    pub time_limit: Duration,
+}
+
+/// Returns a program of the tests running the shell snippet, e.g. a `StreamSide`.
+#[cfg(test)]
+pub(crate) fn exec_fixture<T: From<ExecFixture>>(command: &str) -> T {
+    T::from(ExecFixture {
+        lang: RunLang::Exec,
+        prog: command.into(),
+        time_limit: Duration::from_secs(10),
+    })
+}
@@ -0,0 +1,304 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    time::Duration,
+};
+
+use tokio::{
+    fs,
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader, Result},
+    process::{Child, Command},
+    time::{timeout_at, Instant},
+};
+
+use crate::core::{
+    check::Verdict,
+    compile::RunLang,
+    diff::LINE_WIDTH,
+    run_code::{generate_file_with_random_name, invocation, kill_process_group, own_process_group},
+    string::{escape_bytes, truncate_line},
+};
+
+/// The number of bytes of the standard error kept to report a runtime error.
+const STDERR_KEPT: usize = 4096;
+
+const CR_TIMEOUT: &str = "The correct answer ran out of time";
+
+/// A program to run for the streaming comparison.
+#[derive(Clone, Debug)]
+pub struct StreamSide {
+    pub lang: RunLang,
+    /// The program, as returned by `compile`.
+    pub prog: PathBuf,
+    pub time_limit: Duration,
+}
+
+/// Runs cr and wr on the same input at once, and compares their standard outputs line by line as
+/// they're printed, the way `LineChecker` does. Only a line of each output is held at a time, and
+/// both programs are killed as soon as a difference is found.
+///
+/// The standard errors aren't compared. Instead, a program exiting with a failure is a runtime
+/// error, reported along with the start of its standard error.
+pub async fn compare_streams(cr: StreamSide, wr: StreamSide, input: String) -> Result<Verdict> {
+    let input_loc = generate_file_with_random_name("./compile/temp/", &input).await?;
+    let result = run_both(cr, wr, &input_loc).await;
+    fs::remove_file(input_loc).await?;
+    result
+}
+
+async fn run_both(cr: StreamSide, wr: StreamSide, input_loc: &Path) -> Result<Verdict> {
+    let mut cr = RunningSide::spawn(cr, input_loc).await?;
+    let mut wr = RunningSide::spawn(wr, input_loc).await?;
+
+    for line in 1.. {
+        let expected = cr.next_line().await?;
+        let actual = wr.next_line().await?;
+        match (expected, actual) {
+            (Line::Timeout, _) => {
+                return Ok(Verdict::Fail(CR_TIMEOUT.to_owned()));
+            }
+            (_, Line::Timeout) => return Ok(Verdict::WrongAnswer("Timeout".to_owned())),
+            (Line::End, Line::End) => break,
+            (Line::Some, Line::Some) if cr.line() == wr.line() => continue,
+            (Line::Some, Line::Some) => {
+                return Ok(Verdict::WrongAnswer(format!(
+                    "Line {line} differs: expected {}, found {}",
+                    truncate_line(&escape_bytes(cr.line()), LINE_WIDTH),
+                    truncate_line(&escape_bytes(wr.line()), LINE_WIDTH)
+                )));
+            }
+            // Blank lines at the end are ignored
+            (Line::Some, Line::End) => match cr.rest_is_blank().await? {
+                Line::Timeout => {
+                    return Ok(Verdict::Fail(CR_TIMEOUT.to_owned()));
+                }
+                Line::End => break,
+                _ => {
+                    return Ok(Verdict::WrongAnswer(format!(
+                        "Output ends early at line {line}"
+                    )))
+                }
+            },
+            (Line::End, Line::Some) => match wr.rest_is_blank().await? {
+                Line::Timeout => return Ok(Verdict::WrongAnswer("Timeout".to_owned())),
+                Line::End => break,
+                _ => {
+                    return Ok(Verdict::WrongAnswer(format!(
+                        "Extra output from line {line}"
+                    )))
+                }
+            },
+        }
+    }
+
+    match (cr.wait().await?, wr.wait().await?) {
+        (None, _) => Ok(Verdict::Fail(CR_TIMEOUT.to_owned())),
+        (_, None) => Ok(Verdict::WrongAnswer("Timeout".to_owned())),
+        (Some(status), _) if !status.success() => Ok(Verdict::Fail(format!(
+            "The correct answer exited with {status}{}",
+            cr.stderr().await
+        ))),
+        (_, Some(status)) if !status.success() => Ok(Verdict::WrongAnswer(format!(
+            "Runtime error, exited with {status}{}",
+            wr.stderr().await
+        ))),
+        _ => Ok(Verdict::Accepted),
+    }
+}
+
+/// The result of reading from an output.
+enum Line {
+    Some,
+    End,
+    Timeout,
+}
+
+struct RunningSide {
+    child: Child,
+    stdout: BufReader<tokio::process::ChildStdout>,
+    stderr: tokio::task::JoinHandle<Vec<u8>>,
+    pid: Option<u32>,
+    deadline: Instant,
+    buf: Vec<u8>,
+}
+
+impl RunningSide {
+    async fn spawn(side: StreamSide, input_loc: &Path) -> Result<Self> {
+        let input_file = fs::File::open(input_loc).await?;
+        let (program, args) = invocation(side.lang, &side.prog);
+        let mut command = std::process::Command::new(program);
+        command
+            .args(args)
+            .stdin(Stdio::from(input_file.into_std().await))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        own_process_group(&mut command);
+        let mut child = Command::from(command).kill_on_drop(true).spawn()?;
+        let pid = child.id();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let stderr = tokio::spawn(drain(child.stderr.take().unwrap(), STDERR_KEPT));
+        Ok(Self {
+            child,
+            stdout,
+            stderr,
+            pid,
+            deadline: Instant::now() + side.time_limit,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Reads the next line, without its trailing whitespace.
+    async fn next_line(&mut self) -> Result<Line> {
+        self.buf.clear();
+        match timeout_at(self.deadline, self.stdout.read_until(b'\n', &mut self.buf)).await {
+            Err(_) => Ok(Line::Timeout),
+            Ok(read) => match read? {
+                0 => Ok(Line::End),
+                _ => {
+                    let len = self.buf.trim_ascii_end().len();
+                    self.buf.truncate(len);
+                    Ok(Line::Some)
+                }
+            },
+        }
+    }
+
+    fn line(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Reads the rest of the output, which is expected to be blank lines. Returns `Line::Some` at
+    /// the first line which isn't blank.
+    async fn rest_is_blank(&mut self) -> Result<Line> {
+        if !self.line().is_empty() {
+            return Ok(Line::Some);
+        }
+        loop {
+            match self.next_line().await? {
+                Line::Some if self.line().is_empty() => continue,
+                x => return Ok(x),
+            }
+        }
+    }
+
+    /// Waits for the program to exit, and returns None if it ran out of time.
+    async fn wait(&mut self) -> Result<Option<ExitStatus>> {
+        match timeout_at(self.deadline, self.child.wait()).await {
+            Err(_) => Ok(None),
+            Ok(status) => status.map(Some),
+        }
+    }
+
+    /// Returns the start of the standard error to append to a message, if it's not empty.
+    async fn stderr(&mut self) -> String {
+        let stderr = (&mut self.stderr).await.unwrap_or_default();
+        match escape_bytes(&stderr).trim() {
+            "" => String::new(),
+            stderr => format!(": {stderr}"),
+        }
+    }
+}
+
+impl Drop for RunningSide {
+    fn drop(&mut self) {
+        // The processes spawned by the program, e.g. by a shell pipeline, are killed with it
+        kill_process_group(self.pid);
+    }
+}
+
+/// Reads everything from a pipe so that the program doesn't block on it, keeping the first `kept`
+/// bytes.
+async fn drain(mut reader: impl AsyncRead + Unpin, kept: usize) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 4096];
+    while let Ok(n @ 1..) = reader.read(&mut chunk).await {
+        let take = n.min(kept.saturating_sub(buf.len()));
+        buf.extend_from_slice(&chunk[..take]);
+    }
+    buf
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::time::Duration;
+
+    use super::{compare_streams, StreamSide, CR_TIMEOUT};
+    use crate::core::{
+        check::{Checker, LineChecker, Verdict},
+        run_code::{exec_fixture as exec, ExecFixture},
+    };
+
+    impl From<ExecFixture> for StreamSide {
+        fn from(x: ExecFixture) -> Self {
+            Self {
+                lang: x.lang,
+                prog: x.prog,
+                time_limit: x.time_limit,
+            }
+        }
+    }
+
+    async fn compare(cr: StreamSide, wr: StreamSide) -> Verdict {
+        compare_streams(cr, wr, "1 2\n".to_owned()).await.unwrap()
+    }
+
+    fn wrong(message: &str) -> Verdict {
+        Verdict::WrongAnswer(message.to_owned())
+    }
+
+    #[tokio::test]
+    async fn agree_with_line_checker() {
+        let cases: [(&[u8], &[u8]); 4] = [
+            (b"1 2\n3\n", b"1 2  \n3\n\n\n"),
+            (b"1\n\n\n", b"1"),
+            (b"1 2\n3\n", b"1 2\n4\n"),
+            (b"1\n", b"1\n\n2\n"),
+        ];
+        for (expected, actual) in cases {
+            let printf = |x: &[u8]| exec(&format!("printf '{}'", x.escape_ascii()));
+            let streamed = compare(printf(expected), printf(actual)).await;
+            let checked = LineChecker.check("", expected, actual);
+            assert_eq!(streamed.is_accepted(), checked.is_accepted(), "{streamed}");
+        }
+    }
+
+    #[tokio::test]
+    async fn verdicts() {
+        assert!(compare(exec("cat"), exec("read a b; echo $a $b"))
+            .await
+            .is_accepted());
+        assert_eq!(
+            compare(exec("printf '1\\n2\\n'"), exec("printf '1\\n3\\n'")).await,
+            wrong("Line 2 differs: expected 2, found 3")
+        );
+        assert_eq!(
+            compare(exec("printf '1\\n2\\n'"), exec("printf '1\\n'")).await,
+            wrong("Output ends early at line 2")
+        );
+        assert_eq!(
+            compare(exec("printf '1\\n'"), exec("printf '1\\n\\n2\\n'")).await,
+            wrong("Extra output from line 2")
+        );
+        assert_eq!(
+            compare(exec("echo 1"), exec("echo 1; echo oops >&2; exit 1")).await,
+            wrong("Runtime error, exited with exit status: 1: oops")
+        );
+        assert_eq!(
+            compare(exec("echo 1; exit 2"), exec("echo 1")).await,
+            Verdict::Fail("The correct answer exited with exit status: 2".to_owned())
+        );
+
+        let slow = StreamSide {
+            time_limit: Duration::from_millis(200),
+            ..exec("echo 1; sleep 5")
+        };
+        assert_eq!(
+            compare(exec("echo 1"), slow.clone()).await,
+            wrong("Timeout")
+        );
+        assert_eq!(
+            compare(slow, exec("echo 1")).await,
+            Verdict::Fail(CR_TIMEOUT.to_owned())
+        );
+    }
+}
@@ -9,3 +9,79 @@ pub fn process_str(s: &str) -> String {
     builder.truncate(trimmed_len + 1);
     builder
 }
+
+/// Same as `process_str`, but on an output which may not be valid UTF-8. Only ASCII whitespace is
+/// trimmed from the lines of such an output.
+pub fn process_bytes(s: &[u8]) -> Vec<u8> {
+    if let Ok(s) = std::str::from_utf8(s) {
+        return process_str(s).into_bytes();
+    }
+    let mut builder = Vec::new();
+    for l in s.split(|&b| b == b'\n') {
+        builder.extend_from_slice(l.trim_ascii_end());
+        builder.push(b'\n');
+    }
+    let trimmed_len = builder.trim_ascii_end().len();
+    builder.truncate(trimmed_len + 1);
+    builder
+}
+
+/// Renders an output as text, escaping the bytes which aren't valid UTF-8 as `\xNN`.
+pub fn escape_bytes(s: &[u8]) -> String {
+    let mut builder = String::new();
+    for chunk in s.utf8_chunks() {
+        builder.push_str(chunk.valid());
+        for b in chunk.invalid() {
+            write!(builder, "\\x{b:02x}").unwrap();
+        }
+    }
+    builder
+}
+
+/// Cuts a line longer than `width` characters, marking where it's been cut.
+pub fn truncate_line(line: &str, width: usize) -> String {
+    match line.char_indices().nth(width) {
+        None => line.to_owned(),
+        Some((end, _)) => {
+            let rest = line[end..].chars().count();
+            format!("{}... ({rest} more characters)", &line[..end])
+        }
+    }
+}
+
+/// Keeps the first `max_lines` lines of a text, each cut at `width` characters, and notes how
+/// many lines were left out.
+pub fn truncate_text(s: &str, max_lines: usize, width: usize) -> String {
+    let mut builder = String::new();
+    for l in s.lines().take(max_lines) {
+        writeln!(builder, "{}", truncate_line(l, width)).unwrap();
+    }
+    let rest = s.lines().count().saturating_sub(max_lines);
+    if rest > 0 {
+        writeln!(builder, "... ({rest} more lines)").unwrap();
+    }
+    builder
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape_bytes, process_bytes, truncate_line, truncate_text};
+
+    #[test]
+    fn invalid_utf8() {
+        assert_eq!(escape_bytes(b"a\xffb\n"), "a\\xffb\n");
+        assert_eq!(process_bytes(b"1 \xff  \n\n"), b"1 \xff\n");
+        assert_eq!(process_bytes(b"1  \n2\n\n"), b"1\n2\n");
+    }
+
+    #[test]
+    fn truncate() {
+        assert_eq!(truncate_line("abc", 3), "abc");
+        assert_eq!(truncate_line("abcde", 3), "abc... (2 more characters)");
+        assert_eq!(truncate_text("1\n2\n3\n", 5, 10), "1\n2\n3\n");
+        assert_eq!(
+            truncate_text("1\n2\n3\n", 2, 10),
+            "1\n2\n... (1 more lines)\n"
+        );
+    }
+}
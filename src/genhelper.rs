@@ -1 +1,3 @@
+pub mod params;
+pub mod registry;
 pub mod tree_gen;
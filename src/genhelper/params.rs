@@ -0,0 +1,66 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+/// The parameters of a generator, given with `--param KEY=VALUE` or left at the defaults declared
+/// in `generators!`.
+#[derive(Clone, Debug, Default)]
+pub struct Params {
+    values: HashMap<&'static str, String>,
+}
+
+impl Params {
+    /// Returns the declared defaults, overridden by the given values of the keys among them.
+    pub fn new(defaults: Vec<(&'static str, String)>, overrides: &[(String, String)]) -> Self {
+        let mut values: HashMap<_, _> = defaults.into_iter().collect();
+        for (key, value) in overrides {
+            if let Some(x) = values.get_mut(key.as_str()) {
+                x.clone_from(value);
+            }
+        }
+        Self { values }
+    }
+
+    /// Returns the value of a parameter parsed as `T`, e.g. `params.get::<usize>("n")`. The values
+    /// given from the command line are checked against the declared types before generating.
+    ///
+    /// # Panics
+    /// Panics if the parameter isn't declared, or is read as another type than declared.
+    pub fn get<T>(&self, key: &str) -> T
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self
+            .values
+            .get(key)
+            .unwrap_or_else(|| panic!("The parameter {key} isn't declared in generators!"));
+        value
+            .parse()
+            .unwrap_or_else(|e| panic!("Invalid value of the parameter {key}: {value} ({e})"))
+    }
+}
+
+/// Parses parameters given as `KEY=VALUE`.
+pub fn parse_params(specs: &[String]) -> Result<Vec<(String, String)>, String> {
+    specs
+        .iter()
+        .map(|spec| match spec.split_once('=') {
+            Some((key, value)) => Ok((key.trim().to_owned(), value.trim().to_owned())),
+            None => Err(format!("Invalid parameter, expected KEY=VALUE: {spec}")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_params, Params};
+
+    #[test]
+    fn override_defaults() {
+        let overrides = parse_params(&["n=10".to_owned(), "other=1".to_owned()]).unwrap();
+        let defaults = vec![("n", 50000.to_string()), ("maxv", 1e9.to_string())];
+        let params = Params::new(defaults, &overrides);
+        assert_eq!(params.get::<usize>("n"), 10);
+        assert_eq!(params.get::<f64>("maxv"), 1e9);
+        assert!(parse_params(&["n".to_owned()]).is_err());
+    }
+}
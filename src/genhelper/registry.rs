@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::params::parse_params;
+
+/// Declares the generators selectable by name with `--gen`, as `"name" => function` pairs of
+/// async functions taking a `StdRng` and `Params`, and returning a testcase. The first one is used
+/// by default. The parameters of a generator are declared with their types and defaults in braces
+/// after it, e.g. `"random" => generate_input { n: usize = 50000 }`.
+///
+/// Defines `GENERATOR_NAMES`, `param_names`, `check_params` and `generate_named` in the module
+/// it's used in.
+macro_rules! generators {
+    ($(
+        $name:literal => $generator:path $({ $($key:ident: $type:ty = $default:expr),* $(,)? })?
+    ),+ $(,)?) => {
+        /// The names of the generators, the first of which is the default.
+        pub const GENERATOR_NAMES: &[&str] = &[$($name),+];
+
+        /// Returns the names of the parameters declared by the generator of the given name.
+        pub fn param_names(name: &str) -> &'static [&'static str] {
+            match name {
+                $($name => &[$($(stringify!($key)),*)?],)+
+                _ => &[],
+            }
+        }
+
+        /// Checks that the given parameters of the generator of the given name can be parsed as
+        /// the types they're declared with.
+        pub fn check_params(name: &str, overrides: &[(String, String)]) -> Result<(), String> {
+            match name {
+                $($name => {
+                    $($(
+                        let key = stringify!($key);
+                        for (_, value) in overrides.iter().filter(|(x, _)| x == key) {
+                            value.parse::<$type>().map_err(|e| {
+                                format!("Invalid value of the parameter {key}: {value} ({e})")
+                            })?;
+                        }
+                    )*)?
+                })+
+                _ => {}
+            }
+            Ok(())
+        }
+
+        /// Runs the generator of the given name with its default parameters overridden by the
+        /// given ones, or returns None if there's no such generator.
+        pub async fn generate_named(
+            name: &str,
+            rng: StdRng,
+            overrides: &[(String, String)],
+        ) -> Option<String> {
+            match name {
+                $($name => {
+                    let defaults = vec![$($({
+                        let default: $type = $default;
+                        (stringify!($key), default.to_string())
+                    }),*)?];
+                    Some($generator(rng, Params::new(defaults, overrides)).await)
+                })+
+                _ => None,
+            }
+        }
+    };
+}
+
+pub(crate) use generators;
+
+/// Mixed into the seed of a testcase to pick its generator, so that the pick doesn't follow the
+/// first random value of the generator.
+const PICK_SALT: u64 = 0x6a09e667f3bcc909;
+
+/// Named generators with weights, one of which is picked for each testcase by its seed.
+#[derive(Clone, Debug)]
+pub struct GenMix {
+    gens: Vec<(&'static str, u32)>,
+    params: Arc<[(String, String)]>,
+}
+
+impl GenMix {
+    /// Parses generators given as `NAME` or `NAME=WEIGHT`, checking them against the registered
+    /// names. With no generators given, the first registered one is used.
+    pub fn parse(specs: &[String], names: &[&'static str]) -> Result<Self, String> {
+        let mut gens = Vec::with_capacity(specs.len());
+        for spec in specs {
+            let (name, weight) = match spec.split_once('=') {
+                Some((name, weight)) => {
+                    let weight = weight
+                        .parse()
+                        .map_err(|_| format!("Invalid weight of generator {name}: {weight}"))?;
+                    (name, weight)
+                }
+                None => (spec.as_str(), 1),
+            };
+            let name = names.iter().find(|&&x| x == name).ok_or_else(|| {
+                format!(
+                    "Unknown generator: {name} (expected one of {})",
+                    names.join(", ")
+                )
+            })?;
+            gens.push((*name, weight));
+        }
+
+        if gens.is_empty() {
+            gens.push((names[0], 1));
+        }
+        if gens.iter().all(|&(_, weight)| weight == 0) {
+            return Err("The weights of the generators are all zero".to_owned());
+        }
+        Ok(Self {
+            gens,
+            params: Arc::new([]),
+        })
+    }
+
+    /// Sets the parameters given as `KEY=VALUE`, checking that each of them is declared by one of
+    /// the generators, whose parameters are given by `param_names`.
+    pub fn with_params(
+        mut self,
+        specs: &[String],
+        param_names: impl Fn(&str) -> &'static [&'static str],
+    ) -> Result<Self, String> {
+        let params = parse_params(specs)?;
+        let mut declared: Vec<&str> = Vec::new();
+        for &(name, _) in self.gens.iter() {
+            declared.extend(param_names(name));
+        }
+        declared.sort_unstable();
+        declared.dedup();
+        if let Some((key, _)) = params
+            .iter()
+            .find(|(key, _)| !declared.contains(&key.as_str()))
+        {
+            return Err(match declared.is_empty() {
+                true => format!("Unknown parameter: {key} (the generators declare none)"),
+                false => format!(
+                    "Unknown parameter: {key} (expected one of {})",
+                    declared.join(", ")
+                ),
+            });
+        }
+        self.params = params.into();
+        Ok(self)
+    }
+
+    /// Returns the parameters given to the generators.
+    pub fn params(&self) -> Arc<[(String, String)]> {
+        self.params.clone()
+    }
+
+    /// Returns the names of the generators in the mix.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.gens.iter().map(|&(name, _)| name)
+    }
+
+    /// Returns whether testcases may come from different generators.
+    pub fn is_mixed(&self) -> bool {
+        self.gens.len() > 1
+    }
+
+    /// Returns the name of the generator of the testcase of the given seed.
+    pub fn pick(&self, seed: u64) -> &'static str {
+        let total: u64 = self.gens.iter().map(|&(_, weight)| weight as u64).sum();
+        let mut x = StdRng::seed_from_u64(seed ^ PICK_SALT).gen_range(0..total);
+        for &(name, weight) in self.gens.iter() {
+            if x < weight as u64 {
+                return name;
+            }
+            x -= weight as u64;
+        }
+        unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::GenMix;
+    use crate::genhelper::params::Params;
+
+    async fn generate_size(_rng: StdRng, params: Params) -> String {
+        params.get::<u32>("n").to_string()
+    }
+
+    generators! {
+        "size" => generate_size { n: u32 = 5 },
+        "none" => generate_size,
+    }
+
+    #[tokio::test]
+    async fn registered_generators() {
+        assert_eq!(GENERATOR_NAMES, ["size", "none"]);
+        assert_eq!(param_names("size"), ["n"]);
+        assert!(param_names("none").is_empty());
+
+        let rng = || StdRng::seed_from_u64(0);
+        let overrides = [("n".to_owned(), "7".to_owned())];
+        assert_eq!(generate_named("size", rng(), &[]).await.unwrap(), "5");
+        assert_eq!(
+            generate_named("size", rng(), &overrides).await.unwrap(),
+            "7"
+        );
+        assert!(generate_named("other", rng(), &[]).await.is_none());
+
+        assert!(check_params("size", &overrides).is_ok());
+        let invalid = [("n".to_owned(), "abc".to_owned())];
+        let e = check_params("size", &invalid).unwrap_err();
+        assert!(
+            e.starts_with("Invalid value of the parameter n: abc"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn pick_weighted() {
+        let names = ["random", "max", "worst"];
+        let specs = ["random=3".to_owned(), "worst".to_owned()];
+        let mix = GenMix::parse(&specs, &names).unwrap();
+        let picks: Vec<_> = (0..1000).map(|seed| mix.pick(seed)).collect();
+        let random = picks.iter().filter(|&&x| x == "random").count();
+        assert!(picks.iter().all(|&x| x != "max"));
+        assert!((650..850).contains(&random));
+        assert_eq!(mix.pick(42), mix.pick(42));
+
+        let default = GenMix::parse(&[], &names).unwrap();
+        assert!(!default.is_mixed());
+        assert_eq!(default.pick(0), "random");
+        assert!(GenMix::parse(&["min".to_owned()], &names).is_err());
+        assert!(GenMix::parse(&["max=0".to_owned()], &names).is_err());
+
+        let param_names = |name: &str| -> &'static [&'static str] {
+            match name {
+                "random" => &["n"],
+                _ => &[],
+            }
+        };
+        let with_n = mix.clone().with_params(&["n=5".to_owned()], param_names);
+        assert_eq!(
+            with_n.unwrap().params()[0],
+            ("n".to_owned(), "5".to_owned())
+        );
+        assert!(mix
+            .with_params(&["maxv=5".to_owned()], param_names)
+            .is_err());
+    }
+}
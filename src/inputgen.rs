@@ -1,27 +1,63 @@
 #![allow(unused)]
 
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use std::{fmt::Write, future::Future};
 use tokio::task::JoinError;
 
-use crate::{core::string::process_str, genhelper::tree_gen::Tree};
+use crate::{
+    core::string::process_str,
+    genhelper::{
+        params::Params,
+        registry::{generators, GenMix},
+        tree_gen::Tree,
+    },
+};
 
-pub async fn generate_input() -> String {
+// Generators selectable with `--gen <NAME>`, e.g. add `"max" => generate_max` for a function
+// `pub async fn generate_max(mut rng: StdRng, params: Params) -> String` written like
+// `generate_input`. Parameters set with `--param KEY=VALUE` are declared in braces with their types
+// and defaults.
+generators! {
+    "random" => generate_input { n: usize = 50000 },
+}
+
+/// Returns a random testcase. Every random value should come from `rng`, so that the same
+/// testcase is generated again from the same seed. Parameters are read with `params.get`.
+pub async fn generate_input(mut rng: StdRng, params: Params) -> String {
     let mut buf = String::new();
-    let mut rng = thread_rng();
 
     macro_rules! print { ($($arg:tt)*) => { write!(buf, $($arg)*).unwrap(); }; }
     macro_rules! println { ($($arg:tt)*) => { writeln!(buf, $($arg)*).unwrap(); }; }
 
-    let n: usize = rng.gen_range(1..=50000);
+    let n: usize = rng.gen_range(1..=params.get("n"));
     println!("{}", n);
 
     process_str(&buf)
 }
 
-/// Returns an iterator of "num" spawned tasks of generating inputs.
+/// Returns an iterator of spawned tasks of generating inputs, one for each seed, each with the
+/// generator picked from the mix by its seed.
 pub async fn generate_multi(
-    num: usize,
+    seeds: Vec<u64>,
+    mix: &GenMix,
 ) -> impl Iterator<Item = impl Future<Output = Result<String, JoinError>>> {
-    (0..num).map(|_| tokio::spawn(generate_input()))
+    let names: Vec<_> = seeds.iter().map(|&seed| mix.pick(seed)).collect();
+    let params = mix.params();
+    seeds.into_iter().zip(names).map(move |(seed, name)| {
+        let params = params.clone();
+        tokio::spawn(async move {
+            let rng = StdRng::seed_from_u64(seed);
+            // The mix only holds registered names
+            generate_named(name, rng, &params).await.unwrap()
+        })
+    })
+}
+
+/// Returns the seed of the testcase at `index` in a run of the given seed.
+pub fn case_seed(run_seed: u64, index: u64) -> u64 {
+    // SplitMix64, so that the seeds of neighboring testcases look unrelated
+    let mut x = run_seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15));
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
 }
@@ -1,3 +1,4 @@
+pub mod checker;
 pub mod core;
 pub mod genhelper;
 pub mod inputgen;
@@ -47,4 +48,63 @@ mod tests {
 
         Ok(())
     }
+
+    /// Runs a shell command line as a program, and checks that a pipeline running out of time is
+    /// killed as a whole.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn check_exec_command() -> Result<()> {
+        use crate::core::{compile::RunLang, run_code::get_results};
+
+        let inputs = ["1 2".to_owned(), "3 5".to_owned()];
+        let outputs = get_results(
+            RunLang::Exec,
+            "read a b; echo $((a + b)) | cat",
+            inputs,
+            Duration::from_secs(10),
+        )
+        .await;
+        assert_eq!(outputs, [b"3\n", b"8\n"]);
+
+        let outputs = get_results(
+            RunLang::Exec,
+            "sleep 9.25 | cat",
+            ["".to_owned()],
+            Duration::from_millis(200),
+        )
+        .await;
+        assert_eq!(outputs, [b"Timeout"]);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let orphans = std::fs::read_dir("/proc")?
+            .flatten()
+            .filter_map(|x| std::fs::read(x.path().join("cmdline")).ok())
+            .filter(|x| x.starts_with(b"sleep\x009.25"))
+            .count();
+        assert_eq!(orphans, 0);
+
+        Ok(())
+    }
+
+    /// Builds and runs a sample program in the JVM and .NET languages whose toolchains exist.
+    #[tokio::test]
+    async fn check_jvm_and_dotnet_languages() -> Result<()> {
+        use crate::core::{
+            compile::{CompileOptions, RunLang},
+            doctor::{diagnose, probe_version, toolchain},
+        };
+
+        for lang in [RunLang::Kotlin, RunLang::Scala, RunLang::CSharp] {
+            let tools = toolchain(lang);
+            if let Some((program, _)) = tools.iter().find(|(p, a)| probe_version(p, a).is_none()) {
+                eprintln!("Skipping {}: {program} is not installed", lang.name());
+                continue;
+            }
+
+            let report = diagnose(lang, &CompileOptions::default()).await?;
+            assert!(report.is_usable(), "{:?}", report.build);
+        }
+
+        Ok(())
+    }
 }
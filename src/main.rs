@@ -1,13 +1,24 @@
-use std::{fmt::Write, sync::Arc, time::Duration};
+use std::{fmt::Write, fs, io, ops::Range, path::PathBuf, sync::Arc, time::Duration};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use comparer_rust::{
+    checker,
     core::{
-        compile::{compile, RunLang},
-        run_code::get_results,
-        string::process_str,
+        check::{
+            checker_by_name, Checker, FloatChecker, SpecialJudge, StrictChecker, Verdict,
+            FLOAT_ERROR_DEFAULT,
+        },
+        compile::{compile_with, is_project, CompileOptions, Profile, RunLang},
+        config::{memory_limit_bytes, Config, CONFIG_PATH_DEFAULT},
+        diff::{render_diff, CONTEXT_LINES, LINE_WIDTH},
+        doctor::{diagnose, BuildCheck},
+        generator::ExternalGenerator,
+        run_code::{get_outputs, get_results},
+        stream::{compare_streams, StreamSide},
+        string::{escape_bytes, truncate_text},
     },
-    inputgen::generate_multi,
+    genhelper::registry::GenMix,
+    inputgen::{case_seed, check_params, generate_multi, param_names, GENERATOR_NAMES},
 };
 use indicatif::{ProgressBar, ProgressState, ProgressStyle};
 use parking_lot::RwLock;
@@ -16,9 +27,18 @@ use tokio::io::Result;
 const TC_DEFAULT: usize = 100;
 const BATCH_SIZE: usize = 10;
 const TIME_LIMIT_DEFAULT: i64 = 2000; // ms
+const DEBUG_TIME_FACTOR: u32 = 5; // Sanitizers slow the debug build down
+const INPUT_LINES_SHOWN: usize = 20;
+const GEN_DIR: &str = "./compile/gen/";
 
 #[derive(Parser)]
 struct Cli {
+    /// Path to the project config (defaults at ./comparer.toml)
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
+    /// Judge environment from the config to compile in, e.g. atcoder
+    #[clap(long, global = true)]
+    judge: Option<String>,
     #[clap(subcommand)]
     command: Commands,
 }
@@ -29,63 +49,407 @@ enum Commands {
     Inputdebug {
         /// The number of testcases to generate
         num: usize,
+        #[clap(flatten)]
+        seeds: SeedArgs,
+        #[clap(flatten)]
+        gen: GenArgs,
     },
     /// Compare the outputs of two programs and check if they're equal
-    Compare {
-        /// Language of the code of the correct answer
-        cr: String,
-        /// Language of the code of the wrong answer
-        wr: String,
-        /// The number of testcases (defaults at 100)
-        tc: Option<usize>,
-        /// Time limit in milliseconds (defaults at 2000)
-        tl: Option<i64>,
-    },
+    Compare(Box<CompareArgs>),
+    /// Check which languages can be compiled and run on this machine
+    Doctor,
+}
+
+#[derive(Args)]
+struct SeedArgs {
+    /// Seed of the run, from which the seed of each testcase is derived (random by default)
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Generate only the testcase of this seed, as printed along with a wrong answer
+    #[clap(long, conflicts_with = "seed")]
+    replay_seed: Option<u64>,
+}
+
+impl SeedArgs {
+    /// Returns the seed of the run, printing it unless a single testcase is replayed.
+    fn run_seed(&self) -> u64 {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        if self.replay_seed.is_none() {
+            eprintln!("Seed of the run: {seed}");
+        }
+        seed
+    }
+
+    /// Returns the number of testcases to generate, which is 1 when replaying a testcase.
+    fn count(&self, tc: usize) -> usize {
+        match self.replay_seed {
+            Some(_) => 1,
+            None => tc,
+        }
+    }
+
+    /// Returns the seeds of the testcases in the given range of the run.
+    fn case_seeds(&self, run_seed: u64, range: Range<usize>) -> Vec<u64> {
+        match self.replay_seed {
+            Some(seed) => vec![seed],
+            None => range.map(|i| case_seed(run_seed, i as u64)).collect(),
+        }
+    }
+}
+
+#[derive(Args)]
+struct GenArgs {
+    /// Generator of src/inputgen.rs to use, as NAME or NAME=WEIGHT, repeated to mix generators
+    #[clap(long = "gen", value_name = "NAME", conflicts_with = "gen_code")]
+    gens: Vec<String>,
+    /// Parameter of the generators of src/inputgen.rs, as KEY=VALUE
+    #[clap(long = "param", value_name = "KEY=VALUE", conflicts_with = "gen_code")]
+    params: Vec<String>,
+    /// Code of a generator run as `gen <SEED> [ARGS]...`, used instead of generate_input
+    #[clap(long, value_name = "PATH")]
+    gen_code: Option<String>,
+    /// Language of the generator (guessed from the extension of its code by default)
+    #[clap(long, value_name = "LANG")]
+    gen_lang: Option<String>,
+    /// Argument given to the generator after the seed
+    #[clap(long = "gen-arg", value_name = "ARG")]
+    gen_args: Vec<String>,
+}
+
+impl GenArgs {
+    /// Returns the generator selected from the command line, compiling it if it's a program.
+    fn generator(&self, options: &CompileOptions) -> Result<Generator> {
+        let Some(code) = &self.gen_code else {
+            let mix = GenMix::parse(&self.gens, GENERATOR_NAMES)
+                .and_then(|mix| mix.with_params(&self.params, param_names))
+                .and_then(|mix| {
+                    for name in mix.names() {
+                        check_params(name, &mix.params())?;
+                    }
+                    Ok(mix)
+                })
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            return Ok(Generator::Builtin(mix));
+        };
+        let lang: RunLang = match &self.gen_lang {
+            Some(lang) => lang.as_str().try_into()?,
+            None => RunLang::from_path(code).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Can't tell the language of {code}, give it with --gen-lang"),
+                )
+            })?,
+        };
+        let prog = match lang {
+            RunLang::Exec => code.into(),
+            _ => {
+                fs::create_dir_all(GEN_DIR)?;
+                compile_with(lang, code, GEN_DIR, "gen", options)?
+            }
+        };
+        Ok(Generator::External(ExternalGenerator {
+            lang,
+            prog,
+            args: self.gen_args.clone(),
+        }))
+    }
+}
+
+/// Where testcases come from.
+enum Generator {
+    /// The generators of src/inputgen.rs.
+    Builtin(GenMix),
+    External(ExternalGenerator),
+}
+
+impl Generator {
+    /// Generates a testcase for each seed.
+    async fn generate(&self, seeds: Vec<u64>) -> Result<Vec<String>> {
+        match self {
+            Generator::Builtin(mix) => {
+                let mut inputs = Vec::with_capacity(seeds.len());
+                for h in generate_multi(seeds, mix).await {
+                    inputs.push(h.await?);
+                }
+                Ok(inputs)
+            }
+            Generator::External(generator) => generator.generate_multi(seeds).await,
+        }
+    }
+
+    /// Returns the name of the generator of the testcase of the given seed, if several are mixed.
+    fn mixed_name(&self, seed: u64) -> Option<&'static str> {
+        match self {
+            Generator::Builtin(mix) if mix.is_mixed() => Some(mix.pick(seed)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Args)]
+struct CompareArgs {
+    /// Language of the code of the correct answer
+    cr: String,
+    /// Language of the code of the wrong answer
+    wr: String,
+    /// The number of testcases (defaults at 100)
+    tc: Option<usize>,
+    /// Time limit in milliseconds (defaults at 2000)
+    tl: Option<i64>,
+    #[clap(flatten)]
+    seeds: SeedArgs,
+    #[clap(flatten)]
+    gen: GenArgs,
+    /// Command line run as cr when its language is exec
+    #[clap(long, value_name = "CMD")]
+    cr_cmd: Option<String>,
+    /// Command line run as wr when its language is exec
+    #[clap(long, value_name = "CMD")]
+    wr_cmd: Option<String>,
+    /// Language standard or edition as LANG=STD, e.g. cpp=c++20 or rust=2018
+    #[clap(long = "std", value_name = "LANG=STD")]
+    stds: Vec<String>,
+    /// Extra compiler flag as LANG=FLAG, e.g. cpp=-DLOCAL or cpp=-march=native
+    #[clap(long = "flag", value_name = "LANG=FLAG")]
+    flags: Vec<String>,
+    /// Byte-compile Python codes to catch syntax errors before testing
+    #[clap(long)]
+    byte_compile: bool,
+    /// Time limit of each step of compilation in milliseconds (defaults at 60000)
+    #[clap(long)]
+    compile_tl: Option<u64>,
+    /// Memory limit of each step of compilation in MiB (unlimited by default)
+    #[clap(long)]
+    compile_ml: Option<u64>,
+    /// How the outputs are compared: lines, strict, tokens, nocase, unordered-lines,
+    /// unordered-tokens, float, testlib or rust (defaults at lines)
+    #[clap(long, default_value = "lines")]
+    checker: String,
+    /// Language of the testlib checker in compile/checker (defaults at cpp)
+    #[clap(long, default_value = "cpp")]
+    checker_lang: String,
+    /// Command line run as the testlib checker when its language is exec
+    #[clap(long, value_name = "CMD")]
+    checker_cmd: Option<String>,
+    /// Compare the outputs line by line while they're printed, without keeping them in memory
+    #[clap(long)]
+    stream: bool,
+    /// Print the whole input and outputs of each wrong answer instead of a diff
+    #[clap(long)]
+    full: bool,
+    /// Convert CRLF line endings to LF before the strict checker compares the outputs
+    #[clap(long)]
+    crlf: bool,
+    /// Absolute error allowed by the float checker (defaults at 1e-6)
+    #[clap(long, default_value_t = FLOAT_ERROR_DEFAULT)]
+    abs_error: f64,
+    /// Relative error allowed by the float checker (defaults at 1e-6)
+    #[clap(long, default_value_t = FLOAT_ERROR_DEFAULT)]
+    rel_error: f64,
+}
+
+impl CompareArgs {
+    /// Returns the compile options of the config, overridden by the command line.
+    fn compile_options(&self, config: &Config, judge: Option<&str>) -> Result<CompileOptions> {
+        let mut options = config.compile_options(judge)?;
+        options.byte_compile |= self.byte_compile;
+        if let Some(tl) = self.compile_tl {
+            options.limits.time = Some(Duration::from_millis(tl));
+        }
+        if let Some(ml) = self.compile_ml {
+            options.limits.memory = Some(memory_limit_bytes(ml)?);
+        }
+        for s in self.stds.iter() {
+            let (lang, std) = parse_lang_pair(s)?;
+            options.set_std(lang, std);
+        }
+        for s in self.flags.iter() {
+            let (lang, flag) = parse_lang_pair(s)?;
+            options.push_flag(lang, flag);
+        }
+        Ok(options)
+    }
+
+    /// Returns the checker selected from the command line, compiling it if it's a special judge.
+    fn checker(&self, options: &CompileOptions) -> Result<Arc<dyn Checker>> {
+        match self.checker.as_str() {
+            "testlib" => {
+                let lang: RunLang = self.checker_lang.as_str().try_into()?;
+                let prog = prepare(
+                    lang,
+                    "./compile/checker/",
+                    "checker",
+                    self.checker_cmd.as_deref(),
+                    options,
+                )?;
+                Ok(Arc::new(SpecialJudge { lang, prog }))
+            }
+            "rust" => Ok(Arc::new(checker::check)),
+            "strict" => Ok(Arc::new(StrictChecker { crlf: self.crlf })),
+            "float" => Ok(Arc::new(FloatChecker {
+                abs_error: self.abs_error,
+                rel_error: self.rel_error,
+            })),
+            name => checker_by_name(name).map(Arc::from),
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    let config = Config::load(cli.config.unwrap_or_else(|| CONFIG_PATH_DEFAULT.into()))?;
 
     match cli.command {
-        Commands::Inputdebug { num } => {
-            input_debug(num).await?;
-        }
-        Commands::Compare { cr, wr, tc, tl } => {
-            compare(
-                &cr,
-                &wr,
-                tc.unwrap_or(TC_DEFAULT),
-                tl.unwrap_or(TIME_LIMIT_DEFAULT),
-            )
-            .await?;
+        Commands::Inputdebug { num, seeds, gen } => {
+            let generator = gen.generator(&config.compile_options(cli.judge.as_deref())?)?;
+            input_debug(num, &seeds, &generator).await?;
+        }
+        Commands::Compare(args) => {
+            let options = args.compile_options(&config, cli.judge.as_deref())?;
+            compare(&args, &options).await?;
+        }
+        Commands::Doctor => {
+            doctor(&config.compile_options(cli.judge.as_deref())?).await?;
         }
     }
 
     Ok(())
 }
 
+/// Splits an argument of the form `LANG=VALUE` at the first `=`.
+fn parse_lang_pair(s: &str) -> Result<(RunLang, &str)> {
+    let (lang, value) = s.split_once('=').ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Expected LANG=VALUE, got {s}"),
+        )
+    })?;
+    Ok((lang.try_into()?, value))
+}
+
+/// Returns the directory itself if it holds a project, or the path of its main code otherwise.
+fn code_path(lang: RunLang, dir: &str) -> PathBuf {
+    if is_project(lang, dir) {
+        dir.into()
+    } else {
+        PathBuf::from(dir).join(lang.code_file().unwrap_or_default())
+    }
+}
+
 async fn get_actual_time_limit(lang: RunLang, tl: i64) -> Duration {
     use RunLang::*;
     let rtl = match lang {
-        C | Cpp | Rust => tl,
+        C | Cpp | Rust | Haskell | OCaml | Exec => tl,
         Go => tl + 2000,
-        Java => 2 * tl + 1000,
-        Python => 3 * tl + 2000,
+        Java | Kotlin | Scala | CSharp | JavaScript => 2 * tl + 1000,
+        Python | Ruby => 3 * tl + 2000,
+        PyPy => 2 * tl + 1000,
     };
     Duration::from_millis(if rtl < 0 { 0 } else { rtl.unsigned_abs() })
 }
 
-async fn input_debug(num: usize) -> Result<()> {
-    for (tc, h) in generate_multi(num).await.enumerate() {
-        println!("Testcase {}\n```\n{}\n```", tc, h.await?);
+async fn input_debug(num: usize, seeds: &SeedArgs, generator: &Generator) -> Result<()> {
+    let run_seed = seeds.run_seed();
+    let case_seeds = seeds.case_seeds(run_seed, 0..seeds.count(num));
+    let inputs = generator.generate(case_seeds.clone()).await?;
+    for (tc, (input, seed)) in inputs.iter().zip(&case_seeds).enumerate() {
+        match generator.mixed_name(*seed) {
+            Some(name) => println!("Testcase {tc} (seed {seed}, gen {name})\n```\n{input}\n```"),
+            None => println!("Testcase {} (seed {})\n```\n{}\n```", tc, seed, input),
+        }
+    }
+    Ok(())
+}
+
+async fn doctor(options: &CompileOptions) -> Result<()> {
+    let mut usable: Vec<&str> = Vec::new();
+
+    for lang in RunLang::ALL {
+        let report = diagnose(lang, options).await?;
+
+        let status = match &report.build {
+            BuildCheck::Ok => "ok".to_owned(),
+            BuildCheck::DynamicOnly(flags) => format!(
+                "static linking failed, set flags = {flags:?} under [lang.{}] in comparer.toml to build without -static",
+                lang.name()
+            ),
+            BuildCheck::Failed(e) => format!("failed ({e})"),
+            BuildCheck::Skipped => "skipped, missing tools".to_owned(),
+        };
+        println!("[{}] {status}", lang.name());
+        for (program, version) in report.tools.iter() {
+            println!(
+                "    {program}: {}",
+                version.as_deref().unwrap_or("not found")
+            );
+        }
+
+        if report.is_usable() {
+            usable.push(lang.name());
+        }
+    }
+
+    println!();
+    match usable.is_empty() {
+        true => println!("No languages are usable"),
+        false => println!("Usable languages: {}", usable.join(", ")),
     }
     Ok(())
 }
 
-async fn compare(cr: &str, wr: &str, tc: usize, tl: i64) -> Result<()> {
-    let cr_lang: RunLang = cr.try_into()?;
-    let wr_lang: RunLang = wr.try_into()?;
+/// Prints both outputs of a wrong answer, or a diff of them unless `full` is set.
+fn report_outputs(cr_result: &[u8], wr_result: &[u8], full: bool) {
+    if full {
+        println!("Correct Answer");
+        println!("{}", escape_bytes(cr_result));
+        println!("Wrong Output");
+        println!("{}", escape_bytes(wr_result));
+    } else {
+        println!("Difference (- correct answer, + wrong output)");
+        print!(
+            "{}",
+            render_diff(
+                &escape_bytes(cr_result),
+                &escape_bytes(wr_result),
+                CONTEXT_LINES
+            )
+        );
+    }
+}
+
+/// Returns the program to run for one side of the comparison, which is either compiled from the
+/// code in `dir`, or the command line itself for exec.
+fn prepare(
+    lang: RunLang,
+    dir: &str,
+    name: &str,
+    command: Option<&str>,
+    options: &CompileOptions,
+) -> Result<PathBuf> {
+    match (lang, command) {
+        (RunLang::Exec, Some(command)) => Ok(command.into()),
+        (RunLang::Exec, None) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("exec needs a command line given with --{name}-cmd"),
+        )),
+        _ => compile_with(lang, code_path(lang, dir), dir, name, options),
+    }
+}
+
+async fn compare(args: &CompareArgs, options: &CompileOptions) -> Result<()> {
+    let cr_lang: RunLang = args.cr.as_str().try_into()?;
+    let wr_lang: RunLang = args.wr.as_str().try_into()?;
+    let tc = args.seeds.count(args.tc.unwrap_or(TC_DEFAULT));
+    let tl = args.tl.unwrap_or(TIME_LIMIT_DEFAULT);
+    let checker = args.checker(options)?;
+    let generator = args.gen.generator(options)?;
+    if args.stream && args.checker != "lines" {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--stream only compares outputs like the lines checker",
+        ));
+    }
 
     let cr_tl = get_actual_time_limit(cr_lang, tl).await;
     let wr_tl = get_actual_time_limit(wr_lang, tl).await;
@@ -94,20 +458,26 @@ async fn compare(cr: &str, wr: &str, tc: usize, tl: i64) -> Result<()> {
     let wrong_writer = wrong_count.clone();
     let sent_count = wrong_count.clone();
 
-    let code_file = match cr_lang {
-        RunLang::C => "main.c",
-        RunLang::Cpp => "main.cpp",
-        RunLang::Python => "main.py",
-        RunLang::Java => "Main.java",
-        RunLang::Go => "main.go",
-        RunLang::Rust => "main.rs",
-    };
-    let cr_code_path = format!("./compile/cr/{code_file}");
-    let wr_code_path = format!("./compile/wr/{code_file}");
+    let cr_prog = prepare(
+        cr_lang,
+        "./compile/cr/",
+        "cr",
+        args.cr_cmd.as_deref(),
+        options,
+    )?;
+    let wr_prog = prepare(
+        wr_lang,
+        "./compile/wr/",
+        "wr",
+        args.wr_cmd.as_deref(),
+        options,
+    )?;
 
-    let cr_prog = compile(cr_lang, &cr_code_path, "./compile/cr/", "cr")?;
-    let wr_prog = compile(wr_lang, &wr_code_path, "./compile/wr/", "wr")?;
+    // The debug build of wr is only made once a wrong answer is found
+    let mut wr_debug_prog: Option<Option<PathBuf>> = None;
+    let wr_debug_tl = wr_tl * DEBUG_TIME_FACTOR;
 
+    let run_seed = args.seeds.run_seed();
     let pb = ProgressBar::new(tc as u64);
     pb.set_style(ProgressStyle::with_template("{spinner:.blue} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos:>7}/{len:7} ({eta})  Found: {wrong_count:<7}")
         .unwrap()
@@ -119,27 +489,126 @@ async fn compare(cr: &str, wr: &str, tc: usize, tl: i64) -> Result<()> {
         let end = (start + BATCH_SIZE).min(tc);
         let batch = end - start;
 
-        let mut inputs: Vec<String> = Vec::with_capacity(batch);
-        for h in generate_multi(batch).await {
-            inputs.push(h.await?);
-        }
-        let inputs: Arc<[String]> = inputs.into();
+        let seeds = args.seeds.case_seeds(run_seed, start..end);
+        let inputs: Arc<[String]> = generator.generate(seeds.clone()).await?.into();
+
+        // The outputs aren't kept when they're compared while streaming
+        let mut results = None;
+        let all_verdicts: Vec<Verdict> = if args.stream {
+            let cr_side = StreamSide {
+                lang: cr_lang,
+                prog: cr_prog.clone(),
+                time_limit: cr_tl,
+            };
+            let wr_side = StreamSide {
+                lang: wr_lang,
+                prog: wr_prog.clone(),
+                time_limit: wr_tl,
+            };
+            let handles: Vec<_> = inputs
+                .iter()
+                .map(|input| {
+                    let (cr_side, wr_side) = (cr_side.clone(), wr_side.clone());
+                    tokio::spawn(compare_streams(cr_side, wr_side, input.clone()))
+                })
+                .collect();
+            let mut verdicts = Vec::with_capacity(batch);
+            for h in handles {
+                verdicts.push(h.await??);
+            }
+            verdicts
+        } else {
+            let cr_results: Arc<[Vec<u8>]> = get_results(cr_lang, &cr_prog, inputs.clone(), cr_tl)
+                .await
+                .into();
+            let wr_results: Arc<[Vec<u8>]> = get_results(wr_lang, &wr_prog, inputs.clone(), wr_tl)
+                .await
+                .into();
+            // Checkers may run programs, e.g. special judges, so they're run apart from the runtime
+            let handles: Vec<_> = (0..batch)
+                .map(|i| {
+                    let checker = checker.clone();
+                    let inputs = inputs.clone();
+                    let (cr_results, wr_results) = (cr_results.clone(), wr_results.clone());
+                    tokio::task::spawn_blocking(move || {
+                        checker.check(&inputs[i], &cr_results[i], &wr_results[i])
+                    })
+                })
+                .collect();
+            let mut verdicts = Vec::with_capacity(batch);
+            for h in handles {
+                verdicts.push(h.await?);
+            }
+            results = Some((cr_results, wr_results));
+            verdicts
+        };
 
-        let cr_results: Vec<String> = get_results(cr_lang, &cr_prog, inputs.clone(), cr_tl).await;
-        let wr_results: Vec<String> = get_results(wr_lang, &wr_prog, inputs.clone(), wr_tl).await;
+        let mut wrongs: Vec<usize> = Vec::new();
+        let mut verdicts: Vec<Verdict> = Vec::new();
+        for (i, verdict) in all_verdicts.into_iter().enumerate() {
+            if !verdict.is_accepted() {
+                wrongs.push(i);
+                verdicts.push(verdict);
+            }
+        }
 
-        let wrongs: Vec<usize> = (0..batch)
-            .filter(|&i| process_str(&cr_results[i]) != process_str(&wr_results[i]))
-            .collect();
+        let mut reports: Vec<Option<String>> = vec![None; wrongs.len()];
+        if !wrongs.is_empty() && Profile::has_checks(wr_lang) {
+            let debug_prog = wr_debug_prog.get_or_insert_with(|| {
+                let mut debug_options = options.clone();
+                debug_options.profile = Profile::Debug;
+                let code_path = code_path(wr_lang, "./compile/wr/");
+                compile_with(
+                    wr_lang,
+                    code_path,
+                    "./compile/wr/",
+                    "wr-debug",
+                    &debug_options,
+                )
+                .map_err(|e| eprintln!("Debug build of wr failed, skipping reruns: {e}"))
+                .ok()
+            });
+            if let Some(debug_prog) = debug_prog {
+                let failed: Vec<String> = wrongs.iter().map(|&i| inputs[i].clone()).collect();
+                let outputs = get_outputs(wr_lang, &debug_prog, failed, wr_debug_tl).await;
+                for (report, output) in reports.iter_mut().zip(outputs) {
+                    *report = Some(if output.timed_out {
+                        "Timeout".to_owned()
+                    } else if output.stderr.is_empty() {
+                        "No issues reported".to_owned()
+                    } else {
+                        escape_bytes(&output.stderr)
+                    });
+                }
+            }
+        }
 
         *wrong_writer.write() += wrongs.len();
-        for &i in wrongs.iter() {
+        for ((&i, report), verdict) in wrongs.iter().zip(reports).zip(verdicts) {
+            println!("Seed");
+            println!("{}", seeds[i]);
+            if let Some(name) = generator.mixed_name(seeds[i]) {
+                println!("Generator");
+                println!("{name}");
+            }
             println!("Input");
-            println!("{}", inputs[i]);
-            println!("Correct Answer");
-            println!("{}", cr_results[i]);
-            println!("Wrong Output");
-            println!("{}", wr_results[i]);
+            if args.full {
+                println!("{}", inputs[i]);
+            } else {
+                print!(
+                    "{}",
+                    truncate_text(&inputs[i], INPUT_LINES_SHOWN, LINE_WIDTH)
+                );
+            }
+            if let Some((cr_results, wr_results)) = &results {
+                report_outputs(&cr_results[i], &wr_results[i], args.full);
+            }
+            println!("Verdict");
+            println!("{verdict}");
+            if let Some(report) = report {
+                println!("Debug Build Report");
+                println!("{}", report);
+            }
             println!();
         }
 